@@ -0,0 +1,91 @@
+use crate::dpll::{Literal, VarId};
+
+// A combinational and-inverter graph using AIGER literal numbering: literal 2v is
+// variable v, 2v + 1 its negation, and literals 0 and 1 are the constants false and true.
+// Inputs are variables 1 to num_inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AigCircuit {
+    pub num_inputs: u32,
+    // (lhs, rhs0, rhs1) defines the even literal lhs as rhs0 ∧ rhs1
+    pub ands: Vec<(u32, u32, u32)>,
+    pub outputs: Vec<u32>,
+}
+
+// Tseitin encode a circuit with every output asserted true, returning the number of CNF
+// variables and the clauses. AIGER variable v becomes CNF variable v - 1, so a model's
+// first num_inputs values are an input pattern driving all outputs to 1.
+pub fn from_aig(aig: &AigCircuit) -> (usize, Vec<Vec<Literal>>) {
+    let max_var = aig.ands.iter()
+        .flat_map(|&(l, a, b)| [l, a, b])
+        .chain(aig.outputs.iter().copied())
+        .map(|l| l / 2)
+        .max()
+        .unwrap_or(0)
+        .max(aig.num_inputs);
+
+    // The constants get a variable of their own, fixed to false
+    let constant = max_var as VarId;
+    let mut f = vec![vec![Literal(constant, false)]];
+    let lit = |l: u32| {
+        let var = if l < 2 { constant } else { (l / 2 - 1) as VarId };
+        Literal(var, l & 1 == 0)
+    };
+
+    for &(lhs, a, b) in &aig.ands {
+        let (x, a, b) = (lit(lhs), lit(a), lit(b));
+        f.push(vec![Literal(x.0, false), a]);
+        f.push(vec![Literal(x.0, false), b]);
+        f.push(vec![x, Literal(a.0, !a.1), Literal(b.0, !b.1)]);
+    }
+    for &out in &aig.outputs {
+        f.push(vec![lit(out)]);
+    }
+    (max_var as usize + 1, f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dpll::{solve, Solution};
+
+    // A miter comparing a ∧ b against other, built from the inputs a = 2 and b = 4
+    fn miter(other: (u32, u32)) -> AigCircuit {
+        AigCircuit {
+            num_inputs: 2,
+            ands: vec![
+                (6, 2, 4),
+                (8, other.0, other.1),
+                // The exclusive or of 6 and 8 as ¬(¬(6 ∧ ¬8) ∧ ¬(¬6 ∧ 8))
+                (10, 6, 9),
+                (12, 7, 8),
+                (14, 11, 13),
+            ],
+            outputs: vec![15],
+        }
+    }
+
+    #[test]
+    fn check_aig_and_gate() {
+        let aig = AigCircuit { num_inputs: 2, ands: vec![(6, 2, 4)], outputs: vec![6] };
+        let (n, f) = from_aig(&aig);
+        assert_eq!(n, 4);
+        let Solution::Sat(model) = solve(&f, n) else {
+            panic!("Expected a model.");
+        };
+        assert_eq!(model[..2], [true, true]);
+    }
+
+    #[test]
+    fn check_aig_miter() {
+        // a ∧ 1 differs from a ∧ b exactly when a is set and b is not
+        let (n, f) = from_aig(&miter((2, 1)));
+        let Solution::Sat(model) = solve(&f, n) else {
+            panic!("Expected a model.");
+        };
+        assert_eq!(model[..2], [true, false]);
+
+        // b ∧ a is the same function
+        let (n, f) = from_aig(&miter((4, 2)));
+        assert_eq!(solve(&f, n), Solution::Unsat);
+    }
+}
@@ -0,0 +1,93 @@
+use std::{collections::HashMap, fs, time::{Duration, Instant}};
+
+use crate::{cnf_parsing::parse_cnf_file_into, dpll::{solve_formula, Literal, Solution}};
+
+// How one benchmark file went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchmarkResult {
+    pub name: String,
+    // Whether the solver agreed with the expected verdict
+    pub passed: bool,
+    pub time: Duration,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BenchmarkReport {
+    pub passed: usize,
+    pub failed: usize,
+    // One entry per file solved, in file name order
+    pub results: Vec<BenchmarkResult>,
+}
+
+// Solve every DIMACS file in dir that has an entry in expected, keyed by file name with
+// true meaning satisfiable, and check each verdict. Files without an entry are skipped.
+pub fn run_benchmarks(dir: &str, expected: &HashMap<String, bool>) -> BenchmarkReport {
+    let entries = fs::read_dir(dir).unwrap_or_else(|why| panic!("couldn't read {dir}: {why}"));
+    let mut names: Vec<String> = entries
+        .filter_map(|e| e.ok()?.file_name().into_string().ok())
+        .filter(|name| expected.contains_key(name))
+        .collect();
+    names.sort();
+
+    let mut report = BenchmarkReport::default();
+    for name in names {
+        let mut f: Vec<Vec<Literal>> = Vec::new();
+        parse_cnf_file_into(&format!("{dir}/{name}"), &mut f).unwrap_or_else(|why| panic!("couldn't parse {name}: {why}"));
+        let start = Instant::now();
+        let solution = solve_formula(&f);
+        let time = start.elapsed();
+
+        // Failing to decide a file is never the expected verdict
+        let passed = match solution {
+            Solution::Sat(_) => expected[&name],
+            Solution::Unsat => !expected[&name],
+            Solution::Unknown => false,
+        };
+        if passed {
+            report.passed += 1;
+        } else {
+            report.failed += 1;
+        }
+        report.results.push(BenchmarkResult { name, passed, time });
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_run_benchmarks() {
+        let dir = std::env::temp_dir().join(format!("rust-sat-bench-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("sat.cnf"), "p cnf 2 2\n1 2 0\n-1 0\n").unwrap();
+        fs::write(dir.join("unsat.cnf"), "p cnf 1 2\n1 0\n-1 0\n").unwrap();
+        fs::write(dir.join("notes.txt"), "not a benchmark\n").unwrap();
+        let dir_str = dir.to_str().unwrap();
+
+        let mut expected = HashMap::new();
+        expected.insert("sat.cnf".to_string(), true);
+        expected.insert("unsat.cnf".to_string(), false);
+        let report = run_benchmarks(dir_str, &expected);
+        assert_eq!((report.passed, report.failed), (2, 0));
+        let names: Vec<&str> = report.results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["sat.cnf", "unsat.cnf"]);
+
+        expected.insert("unsat.cnf".to_string(), true);
+        let report = run_benchmarks(dir_str, &expected);
+        assert_eq!((report.passed, report.failed), (1, 1));
+        assert!(!report.results[1].passed);
+
+        // Deciding every variable before the last two takes ten thousand nested decisions,
+        // which the search sees through to the right verdict
+        let n = 10_005;
+        fs::write(dir.join("deep.cnf"), format!("p cnf {n} 2\n{} {n} 0\n-{} -{n} 0\n", n - 1, n - 1)).unwrap();
+        for (verdict, failed) in [(true, 0), (false, 1)] {
+            let expected = HashMap::from([("deep.cnf".to_string(), verdict)]);
+            assert_eq!(run_benchmarks(dir_str, &expected).failed, failed);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
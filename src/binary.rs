@@ -0,0 +1,171 @@
+use std::{fmt::{self, Display}, io::{self, Read, Write}};
+
+use crate::dpll::{Literal, VarId};
+
+const MAGIC: &[u8; 4] = b"RSAT";
+
+// Reasons a formula could not be written in, or read back from, the binary format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryError {
+    // A variable, clause or literal count does not fit in a u32
+    CountTooLarge { count: usize },
+    // A variable id does not leave room for the polarity bit
+    VarTooLarge { var: VarId },
+    // The input does not start with the magic tag or is not a whole number of u32 words
+    NotBinary,
+    // The input ends before the last clause it declares
+    Truncated,
+    // Reading or writing failed, with the kind of error and what it said
+    Io { kind: io::ErrorKind, message: String },
+}
+
+impl Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryError::CountTooLarge { count } => write!(f, "count {count} is too large for the binary format"),
+            BinaryError::VarTooLarge { var } => write!(f, "variable {var} is too large for the binary format"),
+            BinaryError::NotBinary => write!(f, "not a binary formula"),
+            BinaryError::Truncated => write!(f, "binary formula is truncated"),
+            BinaryError::Io { message, .. } => write!(f, "couldn't read or write formula: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for BinaryError {}
+
+impl From<io::Error> for BinaryError {
+    fn from(why: io::Error) -> Self {
+        BinaryError::Io { kind: why.kind(), message: why.to_string() }
+    }
+}
+
+// A literal packed as 2 * var + polarity, which leaves room for variables below 2^31
+#[inline(always)]
+fn pack(lit: Literal) -> Result<u32, BinaryError> {
+    if lit.0 >= 1 << 31 {
+        return Err(BinaryError::VarTooLarge { var: lit.0 });
+    }
+    Ok(2 * lit.0 + lit.1 as u32)
+}
+
+#[inline(always)]
+fn unpack(n: u32) -> Literal {
+    Literal(n >> 1, n & 1 == 1)
+}
+
+#[inline(always)]
+fn count(n: usize) -> Result<u32, BinaryError> {
+    u32::try_from(n).map_err(|_| BinaryError::CountTooLarge { count: n })
+}
+
+// Write a formula in a compact binary form that loads far faster than DIMACS text:
+// a magic tag, the variable and clause counts, then each clause as its length followed
+// by its packed literals, every number a little endian u32. Nothing is written if the
+// formula does not fit the format, but an error writing may leave part of it written.
+pub fn save_binary<W: Write>(f: &[Vec<Literal>], num_vars: usize, mut w: W) -> Result<(), BinaryError> {
+    let mut words = vec![count(num_vars)?, count(f.len())?];
+    for c in f {
+        words.push(count(c.len())?);
+        for &lit in c {
+            words.push(pack(lit)?);
+        }
+    }
+
+    let bytes: Vec<u8> = words.iter().flat_map(|n| n.to_le_bytes()).collect();
+    w.write_all(MAGIC)?;
+    w.write_all(&bytes)?;
+    Ok(())
+}
+
+// Read a formula written by save_binary, returning the variable count and the clauses
+pub fn load_binary<R: Read>(mut r: R) -> Result<(usize, Vec<Vec<Literal>>), BinaryError> {
+    let mut bytes = Vec::new();
+    r.read_to_end(&mut bytes)?;
+    if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC || bytes.len() % 4 != 0 {
+        return Err(BinaryError::NotBinary);
+    }
+
+    let mut words = bytes[MAGIC.len()..].chunks_exact(4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+    let mut next = || words.next().ok_or(BinaryError::Truncated);
+    let num_vars = next()? as usize;
+    let num_clauses = next()? as usize;
+    // The counts are not trusted for allocation, a truncated input runs out of words first
+    let mut f = Vec::new();
+    for _ in 0..num_clauses {
+        let len = next()? as usize;
+        let mut c = Vec::new();
+        for _ in 0..len {
+            c.push(unpack(next()?));
+        }
+        f.push(c);
+    }
+    Ok((num_vars, f))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cnf_parsing::parse_cnf, dimacs::parse_dimacs};
+
+    #[test]
+    fn check_binary_round_trip() {
+        let (num_vars, f) = parse_dimacs(&parse_cnf("cnfs/uf20-01.cnf")).unwrap();
+        let mut buf = Vec::new();
+        save_binary(&f, num_vars, &mut buf).unwrap();
+        assert_eq!(buf.len(), 4 + 4 * (2 + 91 * 4));
+        assert_eq!(load_binary(&buf[..]), Ok((num_vars, f)));
+
+        let g = vec![vec![], vec![Literal(65535, false), Literal(0, true)]];
+        let mut buf = Vec::new();
+        save_binary(&g, 65536, &mut buf).unwrap();
+        assert_eq!(load_binary(&buf[..]), Ok((65536, g)));
+    }
+
+    #[test]
+    fn check_binary_truncated() {
+        let mut buf = Vec::new();
+        save_binary(&[vec![Literal(0, true), Literal(1, true)]], 2, &mut buf).unwrap();
+        assert_eq!(load_binary(&buf[..buf.len() - 4]), Err(BinaryError::Truncated));
+        assert_eq!(load_binary(&buf[..6]), Err(BinaryError::NotBinary));
+        assert_eq!(load_binary(&b"RSA"[..]), Err(BinaryError::NotBinary));
+        assert_eq!(load_binary(&b"CNF!\0\0\0\0"[..]), Err(BinaryError::NotBinary));
+        assert_eq!(load_binary(&b"RSAT"[..]).unwrap_err().to_string(), "binary formula is truncated");
+    }
+
+    #[test]
+    fn check_binary_too_large() {
+        let mut buf = Vec::new();
+        let big = Literal(1 << 31, true);
+        assert_eq!(save_binary(&[vec![big]], u32::MAX as usize, &mut buf), Err(BinaryError::VarTooLarge { var: 1 << 31 }));
+        let n = u32::MAX as usize + 1;
+        assert_eq!(save_binary(&[], n, &mut buf), Err(BinaryError::CountTooLarge { count: n }));
+        assert!(buf.is_empty());
+    }
+
+    // Fails every read and write
+    struct Broken;
+
+    impl Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("device gone"))
+        }
+    }
+
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("device gone"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn check_binary_io_error() {
+        let gone = BinaryError::Io { kind: io::ErrorKind::Other, message: "device gone".to_string() };
+        assert_eq!(save_binary(&[vec![Literal(0, true)]], 1, Broken), Err(gone.clone()));
+        assert_eq!(load_binary(Broken), Err(gone.clone()));
+        assert_eq!(gone.to_string(), "couldn't read or write formula: device gone");
+    }
+}
@@ -0,0 +1,1263 @@
+use std::io::{self, Write};
+
+use crate::{
+    dpll::{check_model, Config, Engine, Literal, Restarts, Solution, Stats, VarId, Watched},
+    heuristics::{Activity, DecisionHeuristic, JeroslowWang},
+    preprocess::unsubsumed,
+};
+
+#[inline(always)]
+fn neg(lit: Literal) -> Literal {
+    Literal(lit.0, !lit.1)
+}
+
+// The factor older activity bumps are worth relative to newer ones
+const ACTIVITY_DECAY: f64 = 0.95;
+
+// Conflicts between restarts are this times the next term of the Luby sequence
+pub const RESTART_INTERVAL: usize = 100;
+// The agility above which agile restarts go back to the top level
+pub const AGILITY_THRESHOLD: f64 = 0.2;
+// How many times its average length the trail must be at a conflict to block a restart
+pub const BLOCK_FACTOR: f64 = 1.4;
+// Conflicts between cutting down the learned clauses
+pub const REDUCE_INTERVAL: usize = 2000;
+
+// The ith term, from 0, of the Luby sequence 1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8, ...
+pub fn luby(mut i: usize) -> usize {
+    // Find the smallest complete block 1, ..., 2^k containing i, then look for i within its two halves
+    let (mut size, mut k) = (1, 0);
+    while size < i + 1 {
+        k += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) / 2;
+        k -= 1;
+        i %= size;
+    }
+    1 << k
+}
+
+// An assignment on the trail, with the level it was made at and the clause that forced it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrailEntry {
+    pub lit: Literal,
+    pub level: usize,
+    // None for decisions and for units asserted at the top level
+    pub reason: Option<Vec<Literal>>,
+}
+
+// A clause of the solver's database as reported by Cdcl::clauses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredClause<'s> {
+    pub literals: &'s [Literal],
+    // Still in use, rather than deleted by a subsumption pass, by cutting down the learned clauses or
+    // for being satisfied when it was added
+    pub live: bool,
+    // Learned from a conflict rather than given
+    pub learned: bool,
+}
+
+// What the solver keeps alongside each clause of watched
+struct ClauseInfo {
+    // Fixed for good, unlike the clause's position, which deleting clauses changes
+    id: usize,
+    // How many decision levels its literals spanned when it was learned, its literal block distance.
+    // None for the clauses given rather than learned.
+    lbd: Option<usize>,
+    // How much the variables of the clause are bumped by when it takes part in a conflict, relative to usual
+    weight: f64,
+}
+
+// What a clause, given or learned, follows from
+#[derive(Debug, Default)]
+struct Derivation {
+    // Its position among the given clauses, None if it was learned
+    given: Option<usize>,
+    // The ids of the clauses resolved to learn it
+    clauses: Vec<usize>,
+    // The variables assigned without decisions whose literals were resolved away, or left out of a given clause
+    roots: Vec<VarId>,
+}
+
+// A conflict driven clause learning solver. Propagation watches the first two literals of
+// every clause, conflicts are analysed to their first unique implication point and the
+// search jumps straight back to the level at which the learned clause becomes unit.
+// Clauses can be added between solves, and everything learned is kept for the next one.
+pub struct Cdcl<'a> {
+    // The given and learned clauses, each kept with its watched literals first, and the assignment over them
+    watched: Watched,
+    info: Vec<ClauseInfo>,
+    // Indexed by clause id, for tracing a refutation back to the given clauses it used. Learned clauses
+    // only say what they were resolved from if tracing, the ids of the clauses are kept regardless.
+    derivations: Vec<Derivation>,
+    tracing: bool,
+    num_given: usize,
+    // The clauses deleted so far with their ids, if they are being kept for clauses to report
+    deleted: Option<Vec<(usize, Vec<Literal>)>>,
+    // For each variable assigned without decisions and without a reason clause, the id of the
+    // clause that was a unit
+    unit_ids: Vec<Option<usize>>,
+    activity: Activity,
+    // Unit clauses learned, these are asserted at the top level rather than stored
+    learned_units: Vec<Literal>,
+    // Set once a contradiction is found without any decisions
+    trivially_unsat: bool,
+    // Conflicts analysed over the solver's lifetime
+    conflicts: usize,
+    // Times the search has gone back to the top level to start deciding afresh, and the
+    // conflict count at which it next will
+    restarts: usize,
+    next_restart: usize,
+    // The trail lengths at every conflict added up, for their average
+    trail_total: usize,
+    // How often assignments have lately flipped their variables, for agile restarts
+    agility: f64,
+    // The conflict count at which the learned clauses are next cut down
+    next_reduce: usize,
+    // The clause made false by the contradiction found without decisions, and the trail at that moment,
+    // with the clause's id
+    last_conflict: Option<(Vec<Literal>, Vec<TrailEntry>)>,
+    refuted_by: Option<usize>,
+    // Where the DRAT proof is written, if anywhere, and the first error writing it. Nothing
+    // more is written after an error.
+    proof: Option<&'a mut dyn Write>,
+    proof_error: Option<io::Error>,
+    // Chooses decisions in place of the activities if set
+    heuristic: Option<Box<dyn DecisionHeuristic + 'a>>,
+    // Only max_depth, phase_saving, core_lbd_threshold and the restart options apply, there is no pure literal step
+    config: Config,
+    // The value each variable had when a backtrack last unassigned it, decided again when phase saving
+    phases: Vec<Option<bool>>,
+    stats: Stats,
+    // How much of the trail has been counted in stats
+    counted: usize,
+    // Decided in order, one per level, before any other decision
+    assumptions: Vec<Literal>,
+    // The assumptions the last solve found contradicted by the formula, if it ended in Unsat
+    failed: Vec<Literal>,
+}
+
+impl<'a> Cdcl<'a> {
+    pub fn new(f: &[Vec<Literal>], num_vars: usize) -> Self {
+        let mut solver = Cdcl {
+            watched: Watched::new(num_vars),
+            info: Vec::new(),
+            derivations: Vec::new(),
+            tracing: false,
+            num_given: 0,
+            deleted: None,
+            unit_ids: vec![None; num_vars],
+            activity: Activity::new(num_vars, ACTIVITY_DECAY),
+            learned_units: Vec::new(),
+            trivially_unsat: false,
+            conflicts: 0,
+            restarts: 0,
+            next_restart: RESTART_INTERVAL * luby(0),
+            trail_total: 0,
+            agility: 0.0,
+            next_reduce: REDUCE_INTERVAL,
+            last_conflict: None,
+            refuted_by: None,
+            proof: None,
+            proof_error: None,
+            heuristic: None,
+            config: Config::default(),
+            phases: vec![None; num_vars],
+            stats: Stats { engine: Engine::Cdcl, ..Stats::default() },
+            counted: 0,
+            assumptions: Vec::new(),
+            failed: Vec::new(),
+        };
+        for c in f {
+            solver.add_clause(c);
+        }
+        solver
+    }
+
+    // Add a clause to the formula for this and every later solve. It is simplified by the
+    // assignments made without decisions: dropped if one satisfies it, and the literals they
+    // make false left out. Decisions are only undone as far as needed to propagate it.
+    pub fn add_clause(&mut self, c: &[Literal]) {
+        let id = self.derivations.len();
+        let given = Some(self.num_given);
+        self.num_given += 1;
+        let mut c = c.to_vec();
+        c.sort_by_key(|lit| (lit.0, lit.1));
+        c.dedup();
+        // Tautologies are satisfied whatever happens
+        let w = &self.watched;
+        let at_root = |lit: Literal, b: bool| w.value(lit) == Some(b) && w.level[lit.0 as usize] == 0;
+        if c.windows(2).any(|w| w[0].0 == w[1].0) || c.iter().any(|&lit| at_root(lit, true)) {
+            self.derivations.push(Derivation { given, ..Derivation::default() });
+            self.stats.clauses_deleted += 1;
+            if let Some(deleted) = &mut self.deleted {
+                deleted.push((id, c));
+            }
+            return;
+        }
+
+        let (false_lits, open): (Vec<Literal>, Vec<Literal>) = c.iter().partition(|&&lit| at_root(lit, false));
+        let roots = false_lits.iter().map(|lit| lit.0).collect();
+        self.derivations.push(Derivation { given, clauses: Vec::new(), roots });
+        match open[..] {
+            [] => {
+                self.backtrack(0);
+                self.root_conflict(c, id);
+            }
+            [lit] => {
+                self.backtrack(0);
+                self.watched.enqueue(lit, None);
+                self.unit_ids[lit.0 as usize] = Some(id);
+            }
+            _ => self.attach_given(open, id),
+        }
+    }
+
+    // Store a given clause of at least two literals, none of them assigned without decisions. It watches
+    // the two that will stay unassigned or true the longest: those not false, then those made false at
+    // the highest levels. If the clause is unit or false under the current assignment, the search goes
+    // back to the level at which it became so and propagates it from there.
+    fn attach_given(&mut self, mut c: Vec<Literal>, id: usize) {
+        let w = &self.watched;
+        let false_at = |lit: Literal| (w.value(lit) == Some(false)).then(|| w.level[lit.0 as usize]);
+        c.sort_by_key(|&lit| false_at(lit).map(std::cmp::Reverse));
+        match (false_at(c[0]), false_at(c[1])) {
+            (_, None) => {}
+            // Both become unassigned together, leaving nothing to propagate
+            (Some(l0), Some(l1)) if l0 == l1 => self.backtrack(l0 - 1),
+            (_, Some(l1)) => self.backtrack(l1),
+        }
+        let (asserting, other) = (c[0], c[1]);
+        let ci = self.watched.attach(c);
+        self.info.push(ClauseInfo { id, lbd: None, weight: 1.0 });
+        if self.watched.value(other) == Some(false) {
+            self.watched.enqueue(asserting, Some(ci));
+        }
+    }
+
+    // Log a DRAT proof to w as the search goes: every learned clause as an addition, every learned
+    // clause deleted as a d line and the empty clause once the formula is refuted
+    pub fn with_proof(mut self, w: &'a mut dyn Write) -> Self {
+        self.proof = Some(w);
+        self
+    }
+
+    // Weight each given clause, in the order given, by the corresponding entry of weights. A variable is
+    // bumped by the weight of the clause it is first found in while analysing a conflict, so the search
+    // turns sooner to the variables of heavier clauses. Clauses are otherwise weighted 1, learned ones included.
+    pub fn with_weights(mut self, weights: &[f64]) -> Self {
+        assert_eq!(weights.len(), self.num_given, "There must be a weight for each of the {} clauses.", self.num_given);
+        for info in &mut self.info {
+            if let Some(k) = self.derivations[info.id].given {
+                info.weight = weights[k];
+            }
+        }
+        self
+    }
+
+    // Start each variable's activity at its Jeroslow-Wang score over the given clauses, the sum of
+    // 2^-|c| over the clauses c it occurs in, so the first decisions favour variables of many short
+    // clauses rather than the lowest numbered. Conflicts then bump the activities as usual.
+    pub fn with_static_activity(mut self) -> Self {
+        let given: Vec<Vec<Literal>> = self
+            .watched
+            .clauses
+            .iter()
+            .zip(&self.info)
+            .filter(|(_, info)| info.lbd.is_none())
+            .map(|(c, _)| c.clone())
+            .collect();
+        let jw = JeroslowWang::new(&given, self.watched.assigns.len());
+        let scores = (0..self.watched.assigns.len() as VarId)
+            .map(|v| jw.score(Literal(v, true)) + jw.score(Literal(v, false)))
+            .collect();
+        self.activity = Activity::from_scores(scores, ACTIVITY_DECAY);
+        self
+    }
+
+    // Keep the clauses deleted from now on, so that clauses still reports them
+    pub fn with_deleted_clauses(mut self) -> Self {
+        self.deleted = Some(Vec::new());
+        self
+    }
+
+    // Every clause stored, live or deleted, given or learned, in the order they were added. Units are
+    // assigned rather than stored and do not appear, and deleted clauses only do if the solver was made
+    // with_deleted_clauses.
+    pub fn clauses(&self) -> impl Iterator<Item = StoredClause<'_>> {
+        let live = self.watched.clauses.iter().zip(&self.info).map(|(c, info)| (info.id, c, true));
+        let deleted = self.deleted.iter().flatten().map(|(id, c)| (*id, c, false));
+        let mut all: Vec<(usize, &Vec<Literal>, bool)> = live.chain(deleted).collect();
+        all.sort_unstable_by_key(|c| c.0);
+        let derivations = &self.derivations;
+        all.into_iter().map(move |(id, c, live)| StoredClause {
+            literals: c,
+            live,
+            learned: derivations[id].given.is_none(),
+        })
+    }
+
+    // Search as configured by config, giving up with Unknown past config.max_depth decision levels
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    // Let heuristic choose each decision literal instead of the activities
+    pub fn with_heuristic(mut self, heuristic: Box<dyn DecisionHeuristic + 'a>) -> Self {
+        self.heuristic = Some(heuristic);
+        self
+    }
+
+    // How the search has gone over the solver's lifetime. Every assignment that is not a decision
+    // counts as a propagation, and each jump back after a conflict as a backtrack.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    // Write a clause to the proof as a line of DIMACS literals ending in 0, after prefix
+    fn log(&mut self, prefix: &str, c: &[Literal]) {
+        let (Some(w), None) = (&mut self.proof, &self.proof_error) else {
+            return;
+        };
+        let line: String = c.iter().map(|lit| format!("{lit} ")).collect();
+        if let Err(why) = writeln!(w, "{prefix}{line}0") {
+            self.proof_error = Some(why);
+        }
+    }
+
+    // The first error writing the proof, which is incomplete from that point on
+    pub fn take_proof_error(&mut self) -> Option<io::Error> {
+        self.proof_error.take()
+    }
+
+    // The clauses learned from conflicts so far, units included
+    pub fn learned(&self) -> Vec<Vec<Literal>> {
+        let units = self.learned_units.iter().map(|&lit| vec![lit]);
+        let clauses = self.watched.clauses.iter().zip(&self.info).filter(|c| c.1.lbd.is_some()).map(|c| c.0.clone());
+        units.chain(clauses).collect()
+    }
+
+    // Conflicts analysed over the solver's lifetime
+    pub fn conflicts(&self) -> usize {
+        self.conflicts
+    }
+
+    pub fn restarts(&self) -> usize {
+        self.restarts
+    }
+
+    // The assignments made so far in the current search, in order
+    pub fn trail(&self) -> &[Literal] {
+        &self.watched.trail
+    }
+
+    pub fn decision_level(&self) -> usize {
+        self.watched.decision_level()
+    }
+
+    // The clause falsified when the formula was found unsatisfiable, with the assignments that
+    // falsified it in the order they were made. None unless solving ended in Unsat.
+    pub fn last_conflict(&self) -> Option<(Vec<Literal>, Vec<TrailEntry>)> {
+        self.last_conflict.clone()
+    }
+
+    // Remember what each learned clause was resolved from, so that used_clauses can trace a refutation
+    pub fn with_antecedents(mut self) -> Self {
+        self.tracing = true;
+        self
+    }
+
+    // The positions among the given clauses of those a refutation was derived from, in increasing order.
+    // Learned clauses are followed back to the clauses resolved to learn them, and literals assigned
+    // without decisions back to the clauses that forced them. None unless solving ended in Unsat
+    // and the solver was made with_antecedents.
+    pub fn used_clauses(&self) -> Option<Vec<usize>> {
+        let (Some(id), Some((clause, _)), true) = (self.refuted_by, &self.last_conflict, self.tracing) else {
+            return None;
+        };
+        let mut used = Vec::new();
+        let mut seen_ids = vec![false; self.derivations.len()];
+        let mut seen_vars = vec![false; self.watched.assigns.len()];
+        let mut ids = vec![id];
+        let mut vars: Vec<VarId> = clause.iter().map(|lit| lit.0).collect();
+        loop {
+            if let Some(v) = vars.pop() {
+                if std::mem::replace(&mut seen_vars[v as usize], true) {
+                    continue;
+                }
+                match self.watched.reason[v as usize] {
+                    Some(ci) => {
+                        ids.push(self.info[ci].id);
+                        vars.extend(self.watched.clauses[ci].iter().map(|lit| lit.0).filter(|&u| u != v));
+                    }
+                    None => ids.push(self.unit_ids[v as usize].expect("Every literal without a reason was a unit.")),
+                }
+            } else if let Some(id) = ids.pop() {
+                if std::mem::replace(&mut seen_ids[id], true) {
+                    continue;
+                }
+                let derivation = &self.derivations[id];
+                used.extend(derivation.given);
+                ids.extend(&derivation.clauses);
+                vars.extend(&derivation.roots);
+            } else {
+                break;
+            }
+        }
+        used.sort_unstable();
+        Some(used)
+    }
+
+    // Remember a contradiction found at the top level in the clause with the given id, only the first one is kept
+    fn root_conflict(&mut self, clause: Vec<Literal>, id: usize) {
+        self.trivially_unsat = true;
+        if self.last_conflict.is_some() {
+            return;
+        }
+        self.refuted_by = Some(id);
+        let trail = self.watched.trail.iter()
+            .map(|&lit| TrailEntry {
+                lit,
+                level: self.watched.level[lit.0 as usize],
+                reason: self.watched.reason[lit.0 as usize].map(|ci| self.watched.clauses[ci].clone()),
+            })
+            .collect();
+        self.last_conflict = Some((clause, trail));
+    }
+
+    // Learn a clause from a conflict at the current level by resolving along the trail until a
+    // single literal from this level remains. Returns the clause, its asserting literal first,
+    // the level to jump back to and, if tracing, what was resolved.
+    fn analyze(&mut self, conflict: usize) -> (Vec<Literal>, usize, Derivation) {
+        let mut derivation = Derivation::default();
+        let mut seen = vec![false; self.watched.assigns.len()];
+        let mut learnt = vec![Literal(0, true)];
+        let mut open = 0;
+        let mut idx = self.watched.trail.len();
+        let mut ci = conflict;
+        let mut p: Option<Literal> = None;
+
+        loop {
+            if self.tracing {
+                derivation.clauses.push(self.info[ci].id);
+            }
+            for &q in &self.watched.clauses[ci] {
+                let v = q.0 as usize;
+                if Some(q.0) == p.map(|p| p.0) || seen[v] {
+                    continue;
+                }
+                if self.watched.level[v] == 0 {
+                    if self.tracing {
+                        derivation.roots.push(q.0);
+                    }
+                    continue;
+                }
+                seen[v] = true;
+                self.activity.bump_by(q.0, self.info[ci].weight);
+                if self.watched.level[v] == self.watched.decision_level() {
+                    open += 1;
+                } else {
+                    learnt.push(q);
+                }
+            }
+
+            // The most recent literal on the trail involved in the conflict
+            loop {
+                idx -= 1;
+                if seen[self.watched.trail[idx].0 as usize] {
+                    break;
+                }
+            }
+            let lit = self.watched.trail[idx];
+            seen[lit.0 as usize] = false;
+            open -= 1;
+            p = Some(lit);
+            if open == 0 {
+                break;
+            }
+            ci = self.watched.reason[lit.0 as usize].unwrap();
+        }
+        learnt[0] = neg(p.unwrap());
+        self.activity.decay();
+
+        // Watch the literal from the highest remaining level second, it is the last to become unassigned
+        let mut back = 0;
+        if learnt.len() > 1 {
+            let max = (1..learnt.len()).max_by_key(|&i| self.watched.level[learnt[i].0 as usize]).unwrap();
+            learnt.swap(1, max);
+            back = self.watched.level[learnt[1].0 as usize];
+        }
+        (learnt, back, derivation)
+    }
+
+    // Count the assignments made since the last call, all of them propagated
+    fn record(&mut self) {
+        for i in self.counted..self.watched.trail.len() {
+            self.assigned(self.watched.trail[i]);
+        }
+        self.stats.propagations += self.watched.trail.len() - self.counted;
+        self.counted = self.watched.trail.len();
+    }
+
+    // Count an assignment in the stats, and in the agility whether it flipped its variable
+    fn assigned(&mut self, lit: Literal) {
+        self.stats.count_assignment(lit);
+        if let Restarts::Agile { smoothing } = self.config.restarts {
+            let flipped = self.phases[lit.0 as usize].is_some_and(|b| b != lit.1);
+            self.agility += smoothing * (f64::from(u8::from(flipped)) - self.agility);
+        }
+    }
+
+    // Undo every assignment above level, saving the values undone
+    fn backtrack(&mut self, level: usize) {
+        if self.watched.decision_level() <= level {
+            return;
+        }
+        self.record();
+        for &lit in &self.watched.trail[self.watched.trail_lim[level]..] {
+            self.phases[lit.0 as usize] = Some(lit.1);
+        }
+        self.watched.backtrack(level);
+        self.counted = self.watched.trail.len();
+    }
+
+    // The next decision literal, None once every variable is assigned
+    fn decide(&mut self) -> Option<Literal> {
+        let mut lit = match &mut self.heuristic {
+            Some(heuristic) => heuristic.decide(&self.watched.assigns, &self.watched.clauses)?,
+            None => Literal(self.pick_branch()?, true),
+        };
+        if let (true, Some(b)) = (self.config.phase_saving, self.phases[lit.0 as usize]) {
+            lit.1 = b;
+        }
+        Some(lit)
+    }
+
+    // Store a learned clause of at least two literals, the first being the one it asserts
+    fn learn(&mut self, c: Vec<Literal>, lbd: usize, derivation: Derivation) -> usize {
+        self.info.push(ClauseInfo { id: self.derivations.len(), lbd: Some(lbd), weight: 1.0 });
+        self.derivations.push(derivation);
+        self.watched.attach(c)
+    }
+
+    // Delete the worse half of the learned clauses, those spanning the most decision levels, leaving
+    // alone the core ones within config.core_lbd_threshold and any that forced a current assignment
+    fn reduce(&mut self) {
+        let w = &self.watched;
+        let locked = |ci: usize| w.reason[w.clauses[ci][0].0 as usize] == Some(ci);
+        let mut candidates: Vec<usize> = (0..w.clauses.len())
+            .filter(|&ci| self.info[ci].lbd.is_some_and(|lbd| lbd > self.config.core_lbd_threshold) && !locked(ci))
+            .collect();
+        // Worst first, the older of two equally bad clauses going first
+        candidates.sort_by_key(|&ci| std::cmp::Reverse(self.info[ci].lbd));
+        candidates.truncate(candidates.len().div_ceil(2));
+
+        let mut keep = vec![true; w.clauses.len()];
+        for &ci in &candidates {
+            keep[ci] = false;
+        }
+        self.delete(&keep);
+    }
+
+    // Delete every stored clause containing all the literals of another at the top level. Given clauses
+    // can only be subsumed by given ones, a learned clause that stood in for one could be cut down later.
+    pub fn remove_subsumed(&mut self) {
+        self.backtrack(0);
+        let w = &self.watched;
+        let locked = |ci: usize| w.reason[w.clauses[ci][0].0 as usize] == Some(ci);
+        let mut keep = unsubsumed(&w.clauses, |ci| self.info[ci].lbd.is_none());
+        for (ci, keep) in keep.iter_mut().enumerate() {
+            *keep |= locked(ci);
+        }
+        self.delete(&keep);
+    }
+
+    // Delete the clauses not to keep, logging each to the proof
+    fn delete(&mut self, keep: &[bool]) {
+        for ci in (0..keep.len()).filter(|&ci| !keep[ci]) {
+            self.stats.clauses_deleted += 1;
+            self.log("d ", &self.watched.clauses[ci].clone());
+            if let Some(deleted) = &mut self.deleted {
+                deleted.push((self.info[ci].id, self.watched.clauses[ci].clone()));
+            }
+        }
+        self.watched.remove_clauses(keep);
+        let mut ci = 0;
+        self.info.retain(|_| {
+            ci += 1;
+            keep[ci - 1]
+        });
+    }
+
+    // The unassigned variable with the highest activity, the lowest numbered one on ties
+    fn pick_branch(&self) -> Option<VarId> {
+        let mut best: Option<VarId> = None;
+        for v in (0..self.watched.assigns.len() as VarId).filter(|&v| self.watched.assigns[v as usize].is_none()) {
+            if best.is_none_or(|b| self.activity.score(v) > self.activity.score(b)) {
+                best = Some(v);
+            }
+        }
+        best
+    }
+
+    pub fn solve(&mut self) -> Solution {
+        self.solve_stoppable(&|| false)
+    }
+
+    // Solve with each of assumptions decided true, in order, before anything else. Unsat means
+    // the formula contradicts them, only a conflict without any decisions refutes the formula itself.
+    // Clauses learned under them follow from the formula alone, so they are kept for later solves.
+    // failed_assumptions then says which of them the formula contradicts.
+    pub fn solve_under(&mut self, assumptions: &[Literal]) -> Solution {
+        self.assumptions = assumptions.to_vec();
+        let solution = self.solve();
+        self.assumptions.clear();
+        solution
+    }
+
+    // Solve, checking stop before every decision and giving up with Unknown once it returns true
+    pub fn solve_stoppable(&mut self, stop: &dyn Fn() -> bool) -> Solution {
+        self.backtrack(0);
+        self.search(usize::MAX, stop)
+    }
+
+    // The assumptions of the last solve_under that the formula contradicts, in the order they were
+    // given: those the propagation refuting one of them started from, along with it. Empty if it
+    // refuted the formula itself or did not end in Unsat. Not every one need be necessary.
+    pub fn failed_assumptions(&self) -> &[Literal] {
+        &self.failed
+    }
+
+    // The assumptions that made lit false, lit among them, by following the reasons back along the trail.
+    // The only decisions are assumptions while any are still to be decided.
+    fn analyze_final(&self, lit: Literal) -> Vec<Literal> {
+        let w = &self.watched;
+        let mut seen = vec![false; w.assigns.len()];
+        seen[lit.0 as usize] = true;
+        let mut decided = vec![false; w.assigns.len()];
+        for &t in w.trail.iter().rev() {
+            let v = t.0 as usize;
+            if !seen[v] || w.level[v] == 0 {
+                continue;
+            }
+            match w.reason[v] {
+                Some(ci) => {
+                    for q in &w.clauses[ci] {
+                        seen[q.0 as usize] = true;
+                    }
+                }
+                None => decided[v] = true,
+            }
+        }
+        self.assumptions.iter()
+            .filter(|&&a| a == lit || decided[a.0 as usize] && w.value(a) == Some(true))
+            .copied()
+            .collect()
+    }
+
+    // Carry on from the current state until there is an answer or budget more conflicts have
+    // been analysed, in which case the result is Unknown and the next call picks up from there
+    fn search(&mut self, budget: usize, stop: &dyn Fn() -> bool) -> Solution {
+        self.failed.clear();
+        let solution = self.run(budget, stop);
+        self.record();
+        self.stats.resolved_by_preprocessing = self.stats.decisions == 0 && solution != Solution::Unknown;
+        solution
+    }
+
+    fn run(&mut self, budget: usize, stop: &dyn Fn() -> bool) -> Solution {
+        if self.trivially_unsat {
+            self.log("", &[]);
+            return Solution::Unsat;
+        }
+
+        let limit = self.conflicts.saturating_add(budget);
+        loop {
+            // Stopping here, with the last learned clause asserted but not yet propagated,
+            // leaves nothing that the next call would do differently
+            if self.conflicts >= limit {
+                return Solution::Unknown;
+            }
+            if let Some(conflict) = self.watched.propagate() {
+                if self.watched.decision_level() == 0 {
+                    self.root_conflict(self.watched.clauses[conflict].clone(), self.info[conflict].id);
+                    self.log("", &[]);
+                    return Solution::Unsat;
+                }
+                self.conflicts += 1;
+                // The level may belong to an assumption that was already true, with no decision of its own
+                let first = self.watched.trail[self.watched.trail_lim[self.watched.decision_level() - 1]];
+                if let (Some(heuristic), None) = (&mut self.heuristic, self.watched.reason[first.0 as usize]) {
+                    heuristic.on_conflict(first);
+                }
+                let trail = self.watched.trail.len();
+                self.trail_total += trail;
+                let blocked = self.config.block_restarts
+                    && trail as f64 > BLOCK_FACTOR * self.trail_total as f64 / self.conflicts as f64;
+                let (learnt, back, derivation) = self.analyze(conflict);
+                let mut levels: Vec<usize> = learnt.iter().map(|lit| self.watched.level[lit.0 as usize]).collect();
+                levels.sort_unstable();
+                levels.dedup();
+                self.log("", &learnt);
+                self.stats.backtracks += 1;
+                self.backtrack(back);
+                if learnt.len() == 1 {
+                    self.learned_units.push(learnt[0]);
+                    self.watched.enqueue(learnt[0], None);
+                    self.unit_ids[learnt[0].0 as usize] = Some(self.derivations.len());
+                    self.derivations.push(derivation);
+                } else {
+                    let asserting = learnt[0];
+                    let ci = self.learn(learnt, levels.len(), derivation);
+                    self.watched.enqueue(asserting, Some(ci));
+                }
+                if self.conflicts >= self.next_reduce {
+                    self.next_reduce += REDUCE_INTERVAL;
+                    self.reduce();
+                }
+                // Everything learned is kept, only the decisions are forgotten. A blocked
+                // Luby restart waits as long again for the same term of the sequence.
+                let due = match self.config.restarts {
+                    Restarts::Luby => self.conflicts >= self.next_restart,
+                    Restarts::Agile { .. } => self.agility > AGILITY_THRESHOLD,
+                };
+                if due {
+                    if !blocked {
+                        self.restarts += 1;
+                        self.backtrack(0);
+                    }
+                    self.next_restart = self.conflicts + RESTART_INTERVAL * luby(self.restarts);
+                }
+                continue;
+            }
+
+            let level = self.watched.decision_level();
+            let lit = match self.assumptions.get(level) {
+                Some(&lit) => match self.watched.value(lit) {
+                    Some(false) => {
+                        self.failed = self.analyze_final(lit);
+                        return Solution::Unsat;
+                    }
+                    // Nothing to decide, but the level is kept so that each assumption has its own
+                    Some(true) => {
+                        self.watched.new_level();
+                        continue;
+                    }
+                    None => lit,
+                },
+                None => match self.decide() {
+                    Some(lit) => lit,
+                    None => return Solution::Sat(self.watched.assigns.iter().map(|v| v.unwrap()).collect()),
+                },
+            };
+            if stop() || level >= self.config.max_depth {
+                return Solution::Unknown;
+            }
+            self.record();
+            self.stats.decisions += 1;
+            self.stats.nodes += 1;
+            self.watched.new_level();
+            self.watched.enqueue(lit, None);
+            self.assigned(lit);
+            self.counted += 1;
+        }
+    }
+}
+
+// A CDCL search stopped when it ran out of budget, holding everything it had learned and the
+// assignment it had reached so that it can carry on rather than start again
+pub struct Resumable {
+    solver: Cdcl<'static>,
+    f: Vec<Vec<Literal>>,
+}
+
+impl Resumable {
+    // Search for up to additional_budget more conflicts
+    pub fn resume(&mut self, additional_budget: usize) -> Solution {
+        let solution = self.solver.search(additional_budget, &|| false);
+        if let Solution::Sat(model) = &solution {
+            check_model(&self.f, model);
+        }
+        solution
+    }
+
+    // Conflicts analysed so far across every call
+    pub fn conflicts(&self) -> usize {
+        self.solver.conflicts
+    }
+}
+
+// Solve a formula with clause learning for at most budget conflicts. The handle can
+// resume an Unknown result with more budget, continuing exactly where this stopped.
+pub fn solve_budgeted(f: &[Vec<Literal>], num_vars: usize, budget: usize) -> (Solution, Resumable) {
+    let mut handle = Resumable { solver: Cdcl::new(f, num_vars), f: f.to_vec() };
+    (handle.resume(budget), handle)
+}
+
+// Solve a formula over num_vars variables with clause learning, writing a DRAT proof of
+// unsatisfiability to proof. The proof is only complete once the result is Unsat. The search
+// carries on past an error writing it, but the error is returned instead of the result.
+pub fn solve_with_proof(f: &[Vec<Literal>], num_vars: usize, proof: &mut dyn Write) -> io::Result<Solution> {
+    let mut solver = Cdcl::new(f, num_vars).with_proof(proof);
+    let solution = solver.solve();
+    if let Some(why) = solver.take_proof_error() {
+        return Err(why);
+    }
+    if let Solution::Sat(model) = &solution {
+        check_model(f, model);
+    }
+    Ok(solution)
+}
+
+// Solve a formula over num_vars variables with clause learning
+pub fn solve_cdcl(f: &[Vec<Literal>], num_vars: usize) -> Solution {
+    let solution = Cdcl::new(f, num_vars).solve();
+    if let Solution::Sat(model) = &solution {
+        check_model(f, model);
+    }
+    solution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cnf_parsing::parse_cnf_file_into, dpll::solve, local_search::Rng};
+
+    // Every pigeon in some hole and no hole holding two, unsatisfiable when pigeons > holes
+    fn pigeonhole(pigeons: VarId, holes: VarId) -> Vec<Vec<Literal>> {
+        let var = |p: VarId, h: VarId| p * holes + h;
+        let mut f: Vec<Vec<Literal>> = (0..pigeons)
+            .map(|p| (0..holes).map(|h| Literal(var(p, h), true)).collect())
+            .collect();
+        for h in 0..holes {
+            for p in 0..pigeons {
+                for q in p + 1..pigeons {
+                    f.push(vec![Literal(var(p, h), false), Literal(var(q, h), false)]);
+                }
+            }
+        }
+        f
+    }
+
+    #[test]
+    fn check_cdcl_pigeonhole() {
+        let f = pigeonhole(5, 4);
+        let mut solver = Cdcl::new(&f, 20);
+        assert_eq!(solver.solve(), Solution::Unsat);
+        assert!(!solver.learned().is_empty());
+
+        assert!(matches!(solve_cdcl(&pigeonhole(4, 4), 16), Solution::Sat(_)));
+    }
+
+    #[test]
+    fn check_cdcl_matches_dpll() {
+        for name in ["uf20-01", "uf20-010", "uf20-0100"] {
+            let mut f: Vec<Vec<Literal>> = Vec::new();
+            parse_cnf_file_into(&format!("cnfs/{name}.cnf"), &mut f).unwrap();
+            assert!(matches!(solve_cdcl(&f, 20), Solution::Sat(_)));
+        }
+
+        // Random 3-CNF around the satisfiability threshold, so both verdicts come up
+        let mut rng = Rng::new(3);
+        for _ in 0..200 {
+            let f: Vec<Vec<Literal>> = (0..43)
+                .map(|_| (0..3).map(|_| Literal(rng.below(10) as VarId, rng.below(2) == 1)).collect())
+                .collect();
+            let expected = solve(&f, 10) == Solution::Unsat;
+            assert_eq!(solve_cdcl(&f, 10) == Solution::Unsat, expected, "{f:?}");
+        }
+    }
+
+    #[test]
+    fn check_cdcl_trivial() {
+        assert_eq!(solve_cdcl(&[], 2), Solution::Sat(vec![true, true]));
+        assert_eq!(solve_cdcl(&[vec![]], 1), Solution::Unsat);
+        assert_eq!(solve_cdcl(&[vec![Literal(0, true)], vec![Literal(0, false)]], 1), Solution::Unsat);
+        // The tautology is dropped, leaving 1 free
+        let f = vec![vec![Literal(0, false)], vec![Literal(1, true), Literal(1, false)]];
+        assert_eq!(solve_cdcl(&f, 2), Solution::Sat(vec![false, true]));
+    }
+
+    #[test]
+    fn check_last_conflict() {
+        let mut solver = Cdcl::new(&pigeonhole(4, 3), 12);
+        assert_eq!(solver.solve(), Solution::Unsat);
+        let (clause, trail) = solver.last_conflict().unwrap();
+        assert!(!clause.is_empty());
+        for lit in &clause {
+            assert!(trail.iter().any(|e| e.lit == neg(*lit)), "{lit:?} is not false in {trail:?}");
+        }
+        // Everything was undone back to the top level before the contradiction
+        assert!(trail.iter().all(|e| e.level == 0));
+        for e in trail.iter().filter_map(|e| e.reason.as_ref().map(|r| (e.lit, r))) {
+            assert!(e.1.contains(&e.0));
+        }
+
+        let mut solver = Cdcl::new(&[vec![Literal(0, true)], vec![Literal(0, false)]], 1);
+        assert_eq!(solver.solve(), Solution::Unsat);
+        let trail = vec![TrailEntry { lit: Literal(0, true), level: 0, reason: None }];
+        assert_eq!(solver.last_conflict(), Some((vec![Literal(0, false)], trail)));
+
+        let mut solver = Cdcl::new(&pigeonhole(3, 3), 9);
+        assert!(matches!(solver.solve(), Solution::Sat(_)));
+        assert_eq!(solver.last_conflict(), None);
+    }
+
+    // Check every line of a DRAT proof without deletions is implied by unit propagation from
+    // the formula and the lines before it, and that it ends in the empty clause
+    fn check_rup_proof(f: &[Vec<Literal>], num_vars: usize, proof: &str) {
+        use crate::dpll::{clause_status, ClauseStatus};
+
+        let mut known = f.to_vec();
+        let lines: Vec<&str> = proof.lines().collect();
+        assert_eq!(lines.last(), Some(&"0"));
+        for line in lines {
+            let mut nums: Vec<i32> = line.split_whitespace().map(|t| t.parse().unwrap()).collect();
+            assert_eq!(nums.pop(), Some(0));
+            let c: Vec<Literal> = nums.into_iter().map(Literal::from_dimacs).collect();
+
+            let mut assigns = vec![None; num_vars];
+            for lit in &c {
+                assigns[lit.0 as usize] = Some(!lit.1);
+            }
+            let mut refuted = false;
+            let mut changed = true;
+            while changed && !refuted {
+                changed = false;
+                for k in &known {
+                    match clause_status(k, &assigns) {
+                        ClauseStatus::Conflict => refuted = true,
+                        ClauseStatus::Unit(lit) => {
+                            assigns[lit.0 as usize] = Some(lit.1);
+                            changed = true;
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            assert!(refuted, "{line} is not implied by unit propagation");
+            known.push(c);
+        }
+    }
+
+    #[test]
+    fn check_drat_proof() {
+        let f = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, true), Literal(1, false)],
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(0, false), Literal(1, false)],
+        ];
+        let mut proof = Vec::new();
+        assert_eq!(solve_with_proof(&f, 2, &mut proof).unwrap(), Solution::Unsat);
+        assert_eq!(String::from_utf8(proof).unwrap(), "-1 0\n0\n");
+
+        let f = pigeonhole(5, 4);
+        let mut proof = Vec::new();
+        assert_eq!(solve_with_proof(&f, 20, &mut proof).unwrap(), Solution::Unsat);
+        let proof = String::from_utf8(proof).unwrap();
+        assert!(proof.lines().count() > 1);
+        check_rup_proof(&f, 20, &proof);
+
+        let mut proof = Vec::new();
+        assert_eq!(solve_with_proof(&[vec![Literal(0, true)], vec![Literal(0, false)]], 1, &mut proof).unwrap(), Solution::Unsat);
+        assert_eq!(proof, b"0\n");
+
+        // Learned clauses cut down are deleted from the proof too
+        let mut proof = Vec::new();
+        let mut solver = Cdcl::new(&[], 3).with_proof(&mut proof);
+        solver.learn(vec![Literal(0, false), Literal(1, false)], 2, Derivation::default());
+        solver.learn(vec![Literal(0, false), Literal(1, false), Literal(2, true)], 3, Derivation::default());
+        solver.reduce();
+        drop(solver);
+        assert_eq!(String::from_utf8(proof).unwrap(), "d -1 -2 3 0\n");
+    }
+
+    // Accepts the first n bytes, then fails every write
+    struct Full {
+        n: usize,
+        failed: bool,
+        writes_after_failing: usize,
+    }
+
+    impl Write for Full {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.failed {
+                self.writes_after_failing += 1;
+            }
+            if buf.len() > self.n {
+                self.failed = true;
+                return Err(io::Error::other("disk full"));
+            }
+            self.n -= buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn check_proof_error() {
+        let mut full = Full { n: 10, failed: false, writes_after_failing: 0 };
+        let why = solve_with_proof(&pigeonhole(5, 4), 20, &mut full).unwrap_err();
+        assert_eq!(why.to_string(), "disk full");
+        assert_eq!(full.writes_after_failing, 0);
+
+        let mut roomy = Full { n: usize::MAX, failed: false, writes_after_failing: 0 };
+        assert_eq!(solve_with_proof(&pigeonhole(5, 4), 20, &mut roomy).unwrap(), Solution::Unsat);
+    }
+
+    #[test]
+    fn check_weights() {
+        // Deciding 0 and then 2 true each runs into a conflict on one pair of clauses
+        let f = vec![
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(0, false), Literal(1, false)],
+            vec![Literal(2, false), Literal(3, true)],
+            vec![Literal(2, false), Literal(3, false)],
+        ];
+        let activities = |solver: Cdcl| {
+            let mut solver = solver;
+            assert!(matches!(solver.solve(), Solution::Sat(_)));
+            assert_eq!(solver.conflicts(), 2);
+            (solver.activity.score(1), solver.activity.score(3))
+        };
+
+        // Bumps grow with each conflict, so unweighted the later one counts for more
+        let (first, second) = activities(Cdcl::new(&f, 4));
+        assert!(first < second);
+        // Until the clauses of the earlier conflict are weighted ten times as heavily
+        let (first, second) = activities(Cdcl::new(&f, 4).with_weights(&[10.0, 10.0, 1.0, 1.0]));
+        assert!(first > second, "{first} vs {second}");
+        assert_eq!(first, 10.0);
+    }
+
+    #[test]
+    fn check_static_activity() {
+        // 2 is in the most short clauses and 0 only in the longest
+        let f = vec![
+            vec![Literal(0, true), Literal(1, true), Literal(2, true)],
+            vec![Literal(2, true), Literal(3, true)],
+            vec![Literal(2, false), Literal(1, true)],
+        ];
+        let solver = Cdcl::new(&f, 4).with_static_activity();
+        let scores: Vec<f64> = (0..4).map(|v| solver.activity.score(v)).collect();
+        assert_eq!(scores, [0.125, 0.375, 0.625, 0.25]);
+
+        // So the first decision is on 2 instead of 0
+        let mut solver = solver;
+        assert!(matches!(solver.solve(), Solution::Sat(_)));
+        assert_eq!(solver.trail()[0], Literal(2, true));
+        let mut plain = Cdcl::new(&f, 4);
+        assert!(matches!(plain.solve(), Solution::Sat(_)));
+        assert_eq!(plain.trail()[0], Literal(0, true));
+    }
+
+    #[test]
+    fn check_clauses() {
+        let f = vec![
+            vec![Literal(0, true), Literal(1, true), Literal(2, true)],
+            vec![Literal(1, true), Literal(0, true)],
+            vec![Literal(0, false), Literal(2, true), Literal(3, true)],
+            vec![Literal(3, true), Literal(4, true)],
+        ];
+        let mut solver = Cdcl::new(&f, 5).with_deleted_clauses();
+        // A tautology is deleted as soon as it is added
+        solver.add_clause(&[Literal(4, true), Literal(4, false)]);
+        solver.remove_subsumed();
+
+        let clause = |literals, live| StoredClause { literals, live, learned: false };
+        assert_eq!(solver.clauses().collect::<Vec<StoredClause>>(), vec![
+            clause(&f[0], false),
+            clause(&[Literal(0, true), Literal(1, true)], true),
+            clause(&f[2], true),
+            clause(&f[3], true),
+            clause(&[Literal(4, false), Literal(4, true)], false),
+        ]);
+
+        // Long enough a search to cut down the learned clauses
+        let mut solver = Cdcl::new(&pigeonhole(8, 7), 56).with_deleted_clauses();
+        assert!(solver.clauses().all(|c| c.live && !c.learned));
+        assert_eq!(solver.solve(), Solution::Unsat);
+        let learned = solver.clauses().filter(|c| c.live && c.learned).count();
+        let units = solver.learned().iter().filter(|c| c.len() == 1).count();
+        assert_eq!(learned, solver.learned().len() - units);
+        assert!(solver.clauses().any(|c| !c.live && c.learned));
+        assert!(solver.clauses().filter(|c| !c.learned).all(|c| c.live));
+    }
+
+    #[test]
+    fn check_add_clause_mid_search() {
+        // Decide ¬0 then ¬1, each at its own level
+        let mut solver = Cdcl::new(&[], 6);
+        for lit in [Literal(0, false), Literal(1, false)] {
+            solver.watched.new_level();
+            solver.watched.enqueue(lit, None);
+        }
+
+        // Watching the unassigned literals leaves the decisions alone
+        solver.add_clause(&[Literal(0, true), Literal(1, true), Literal(2, true), Literal(3, true)]);
+        assert_eq!(solver.decision_level(), 2);
+        assert_eq!(solver.watched.clauses[0][..2], [Literal(2, true), Literal(3, true)]);
+        // So deciding ¬2 leaves it unit, and propagation finds that
+        solver.watched.new_level();
+        solver.watched.enqueue(Literal(2, false), None);
+        assert_eq!(solver.watched.propagate(), None);
+        assert_eq!(solver.trail(), [Literal(0, false), Literal(1, false), Literal(2, false), Literal(3, true)]);
+        assert_eq!(solver.watched.reason[3], Some(0));
+
+        // A clause already unit asserts its literal at the level that made it so
+        solver.add_clause(&[Literal(4, true), Literal(1, true), Literal(0, true)]);
+        assert_eq!(solver.decision_level(), 2);
+        assert_eq!(solver.trail(), [Literal(0, false), Literal(1, false), Literal(4, true)]);
+        assert_eq!(solver.watched.level[4], 2);
+
+        // A clause made false at a single level goes back to before it, where nothing is left to propagate
+        solver.add_clause(&[Literal(0, true), Literal(1, true), Literal(4, false)]);
+        assert_eq!(solver.decision_level(), 1);
+        assert_eq!(solver.trail(), [Literal(0, false)]);
+
+        // One made false at two goes back to the earlier, asserting its literal from the later
+        solver.watched.new_level();
+        solver.watched.enqueue(Literal(5, true), None);
+        solver.add_clause(&[Literal(0, true), Literal(5, false)]);
+        assert_eq!(solver.decision_level(), 1);
+        assert_eq!(solver.trail(), [Literal(0, false), Literal(5, false)]);
+        assert_eq!(solver.watched.propagate(), None);
+
+        assert!(matches!(solver.solve(), Solution::Sat(_)));
+    }
+
+    #[test]
+    fn check_failed_assumptions() {
+        // Assuming 0 gives 3, then 1 gives 4 before 5, and 4 refutes 2. So 2 fails because of 0 and 1,
+        // although 1 alone would do through 5.
+        let f = vec![
+            vec![Literal(0, false), Literal(3, true)],
+            vec![Literal(1, false), Literal(3, false), Literal(4, true)],
+            vec![Literal(4, false), Literal(2, false)],
+            vec![Literal(1, false), Literal(5, true)],
+            vec![Literal(5, false), Literal(2, false)],
+        ];
+        let mut solver = Cdcl::new(&f, 6);
+        let assumptions = [Literal(0, true), Literal(1, true), Literal(2, true)];
+        assert_eq!(solver.solve_under(&assumptions), Solution::Unsat);
+        assert_eq!(solver.failed_assumptions(), assumptions);
+        assert_eq!(solver.solve_under(&assumptions[1..]), Solution::Unsat);
+        assert_eq!(solver.failed_assumptions(), &assumptions[1..]);
+
+        // Assumptions that play no part are left out, and contradicting each other is enough
+        let both = [Literal(3, true), Literal(5, true), Literal(3, false)];
+        assert_eq!(solver.solve_under(&both), Solution::Unsat);
+        assert_eq!(solver.failed_assumptions(), [Literal(3, true), Literal(3, false)]);
+        assert!(matches!(solver.solve_under(&assumptions[..2]), Solution::Sat(_)));
+        assert!(solver.failed_assumptions().is_empty());
+
+        // Nothing is to blame once the formula itself is refuted
+        solver.add_clause(&[Literal(1, false)]);
+        solver.add_clause(&[Literal(1, true)]);
+        assert_eq!(solver.solve_under(&assumptions), Solution::Unsat);
+        assert!(solver.failed_assumptions().is_empty());
+    }
+
+    #[test]
+    fn check_restarts() {
+        assert_eq!((0..15).map(luby).collect::<Vec<_>>(), [1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+
+        // Long enough to restart several times and cut down the learned clauses along the way
+        let mut solver = Cdcl::new(&pigeonhole(8, 7), 56);
+        assert_eq!(solver.solve(), Solution::Unsat);
+        assert!(solver.conflicts() > REDUCE_INTERVAL);
+        assert!(solver.restarts() >= 2);
+        assert!(solver.stats().clauses_deleted > 0);
+    }
+
+    #[test]
+    fn check_restart_blocking() {
+        // Random 3-CNF at the satisfiability threshold, where the trail at a conflict varies widely
+        let mut rng = Rng::new(5);
+        let f: Vec<Vec<Literal>> = (0..630)
+            .map(|_| (0..3).map(|_| Literal(rng.below(150) as VarId, rng.below(2) == 1)).collect())
+            .collect();
+        let mut plain = Cdcl::new(&f, 150);
+        let blocking = Config { block_restarts: true, ..Config::default() };
+        let mut blocked = Cdcl::new(&f, 150).with_config(blocking);
+        for solver in [&mut plain, &mut blocked] {
+            let Solution::Sat(model) = solver.solve() else { panic!("The formula is satisfiable.") };
+            check_model(&f, &model);
+        }
+        assert!(blocked.restarts() < plain.restarts());
+    }
+
+    #[test]
+    fn check_agile_restarts() {
+        // Conflicts keep moving the pigeons between holes, flipping the same variables back and forth
+        let agile = Config { restarts: Restarts::Agile { smoothing: 0.05 }, ..Config::default() };
+        let mut luby = Cdcl::new(&pigeonhole(6, 5), 30);
+        let mut agile_solver = Cdcl::new(&pigeonhole(6, 5), 30).with_config(agile);
+        assert_eq!(luby.solve(), Solution::Unsat);
+        assert_eq!(agile_solver.solve(), Solution::Unsat);
+        assert!(agile_solver.restarts() > 10 * luby.restarts().max(1));
+
+        let f = pigeonhole(6, 6);
+        let Solution::Sat(model) = Cdcl::new(&f, 36).with_config(agile).solve() else {
+            panic!("Six pigeons fit in six holes.")
+        };
+        check_model(&f, &model);
+    }
+
+    #[test]
+    fn check_reduce() {
+        // Learned clauses whose literals spanned two and three decision levels
+        let learned = [
+            vec![Literal(0, false), Literal(1, false)],
+            vec![Literal(0, false), Literal(1, false), Literal(2, false)],
+        ];
+        let with_threshold = |core_lbd_threshold| {
+            let mut solver = Cdcl::new(&[], 3).with_config(Config { core_lbd_threshold, ..Config::default() });
+            solver.learn(learned[0].clone(), 2, Derivation::default());
+            solver.learn(learned[1].clone(), 3, Derivation::default());
+            solver.reduce();
+            (solver.learned(), solver.stats().clauses_deleted)
+        };
+
+        // By default the LBD 3 clause is the only one eligible, and it goes
+        assert_eq!(with_threshold(2), (learned[..1].to_vec(), 1));
+        // Raising the threshold makes it core
+        assert_eq!(with_threshold(3), (learned.to_vec(), 0));
+        // With both eligible, the half to go is the one spanning more levels
+        assert_eq!(with_threshold(1), (learned[..1].to_vec(), 1));
+    }
+
+    #[test]
+    fn check_resume() {
+        let f = pigeonhole(6, 5);
+        let (solution, full) = solve_budgeted(&f, 30, usize::MAX);
+        assert_eq!(solution, Solution::Unsat);
+
+        let (mut solution, mut handle) = solve_budgeted(&f, 30, 10);
+        assert_eq!(solution, Solution::Unknown);
+        let mut resumes = 0;
+        while solution == Solution::Unknown {
+            solution = handle.resume(10);
+            resumes += 1;
+        }
+        assert_eq!(solution, Solution::Unsat);
+        assert!(resumes > 1);
+        // Resuming carries on the same search, so it takes no more conflicts than one long run
+        assert_eq!(handle.conflicts(), full.conflicts());
+
+        let mut g = Vec::new();
+        parse_cnf_file_into("cnfs/uf20-01.cnf", &mut g).unwrap();
+        let (mut solution, mut handle) = solve_budgeted(&g, 20, 0);
+        while solution == Solution::Unknown {
+            solution = handle.resume(1);
+        }
+        assert!(matches!(solution, Solution::Sat(_)));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::dpll::Literal;
+
+// The verdict of checking a model against a formula
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertResult {
+    Valid,
+    // The first clause the model leaves unsatisfied
+    Invalid { clause_index: usize },
+    // A value line token that is not a literal, or that contradicts an earlier one
+    Malformed { token: String },
+}
+
+#[inline(always)]
+fn satisfies(clause: &[Literal], model: &[bool]) -> bool {
+    clause.iter().any(|lit| model.get(lit.0 as usize) == Some(&lit.1))
+}
+
+// Check that model satisfies every clause of f
+pub fn certify_sat(f: &[Vec<Literal>], model: &[bool]) -> CertResult {
+    let bad = f.iter().position(|clause| !satisfies(clause, model));
+    match bad {
+        Some(clause_index) => CertResult::Invalid { clause_index },
+        None => CertResult::Valid,
+    }
+}
+
+// Check a model printed by another solver as a DIMACS value line, signed literals such as
+// "v 1 -2 3 0" with any number of v markers, up to the 0 that ends it. Variables the line
+// leaves out are unassigned, so a clause only counts as satisfied by a literal it lists.
+pub fn validate_external(f: &[Vec<Literal>], model_line: &str) -> CertResult {
+    let mut assigns: Vec<Option<bool>> = Vec::new();
+    for tok in model_line.split_whitespace().filter(|&tok| tok != "v") {
+        let n: i32 = match tok.parse() {
+            Ok(n) => n,
+            Err(_) => return CertResult::Malformed { token: tok.to_string() },
+        };
+        if n == 0 {
+            break;
+        }
+        let lit = Literal::from_dimacs(n);
+        let v = lit.0 as usize;
+        if v >= assigns.len() {
+            assigns.resize(v + 1, None);
+        }
+        if assigns[v] == Some(!lit.1) {
+            return CertResult::Malformed { token: tok.to_string() };
+        }
+        assigns[v] = Some(lit.1);
+    }
+
+    let bad = f.iter().position(|clause| !clause.iter().any(|lit| assigns.get(lit.0 as usize) == Some(&Some(lit.1))));
+    match bad {
+        Some(clause_index) => CertResult::Invalid { clause_index },
+        None => CertResult::Valid,
+    }
+}
+
+// Number of clauses of f that assigns satisfies
+pub fn num_satisfied(f: &[Vec<Literal>], assigns: &[bool]) -> usize {
+    f.iter().filter(|clause| satisfies(clause, assigns)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_certify_sat() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true), Literal(2, true)],
+            vec![Literal(0, false), Literal(1, true), Literal(2, false)],
+            vec![Literal(1, false), Literal(2, true)],
+        ];
+        assert_eq!(certify_sat(&c, &[true, true, true]), CertResult::Valid);
+        assert_eq!(certify_sat(&c, &[true, false, true]), CertResult::Invalid { clause_index: 1 });
+        assert_eq!(certify_sat(&c, &[false, false, false]), CertResult::Invalid { clause_index: 0 });
+    }
+
+    #[test]
+    fn check_validate_external() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true), Literal(2, true)],
+            vec![Literal(0, false), Literal(1, true), Literal(2, false)],
+            vec![Literal(1, false), Literal(2, true)],
+        ];
+        assert_eq!(validate_external(&c, "v 1 2 3 0"), CertResult::Valid);
+        assert_eq!(validate_external(&c, "v 1 -2\nv 3 0\n"), CertResult::Invalid { clause_index: 1 });
+        // 3 is left out, so the last clause has nothing true in it
+        assert_eq!(validate_external(&c, "v -1 2 0"), CertResult::Invalid { clause_index: 2 });
+        assert_eq!(validate_external(&c, "v 1 2 3 0 -1"), CertResult::Valid);
+
+        assert_eq!(validate_external(&c, "v 1 x 0"), CertResult::Malformed { token: "x".to_string() });
+        assert_eq!(validate_external(&c, "v 1 2 -1 0"), CertResult::Malformed { token: "-1".to_string() });
+    }
+
+    #[test]
+    fn check_num_satisfied() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, false)],
+            vec![Literal(1, false), Literal(2, true)],
+            vec![Literal(2, false)],
+        ];
+        assert_eq!(num_satisfied(&c, &[true, false, true]), 2);
+        assert_eq!(num_satisfied(&c, &[false, true, false]), 3);
+        assert_eq!(num_satisfied(&[], &[true]), 0);
+    }
+}
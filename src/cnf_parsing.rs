@@ -1,6 +1,6 @@
-use std::{path::Path, fs::File, io::prelude::*};
+use std::{path::Path, fs::File, io::{prelude::*, BufReader}};
 
-use crate::dpll::Literal;
+use crate::{dimacs::{parse_dimacs_into, ParseError}, dpll::Literal};
 
 pub fn parse_cnf(fp: &str) -> String {
     let path = Path::new(fp);
@@ -13,6 +13,29 @@ pub fn parse_cnf(fp: &str) -> String {
     s
 }
 
+// Receives clauses one at a time as they are parsed
+pub trait ClauseSink {
+    fn push_clause(&mut self, lits: &[Literal]);
+}
+
+impl ClauseSink for Vec<Vec<Literal>> {
+    fn push_clause(&mut self, lits: &[Literal]) {
+        self.push(lits.to_vec());
+    }
+}
+
+// Stream the clauses of a DIMACS CNF into sink one at a time, only the clause currently
+// being read is held in memory. Returns the number of variables declared in the header.
+pub fn parse_cnf_into<R: BufRead, S: ClauseSink + ?Sized>(reader: R, sink: &mut S) -> Result<usize, ParseError> {
+    parse_dimacs_into(reader, sink)
+}
+
+// Stream the clauses of the DIMACS CNF file at fp into sink
+pub fn parse_cnf_file_into<S: ClauseSink + ?Sized>(fp: &str, sink: &mut S) -> Result<usize, ParseError> {
+    let file = File::open(Path::new(fp)).unwrap_or_else(|_| panic!("File {fp} was not found."));
+    parse_cnf_into(BufReader::new(file), sink)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -22,4 +45,55 @@ mod tests {
         let contents = parse_cnf("cnfs/uf20-01.cnf");
         assert_eq!(contents.lines().next(), Some("c This Formular is generated by mcnf"))
     }
+
+    struct CountingSink(usize);
+
+    impl ClauseSink for CountingSink {
+        fn push_clause(&mut self, _lits: &[Literal]) {
+            self.0 += 1;
+        }
+    }
+
+    #[test]
+    fn check_parse_into_sink() {
+        let contents = "c example\np cnf 3 3\n1 -2 0\n2 3\n-1 0 -3 0\n";
+
+        let mut count = CountingSink(0);
+        assert_eq!(parse_cnf_into(contents.as_bytes(), &mut count), Ok(3));
+        assert_eq!(count.0, 3);
+
+        let mut f: Vec<Vec<Literal>> = Vec::new();
+        parse_cnf_into(contents.as_bytes(), &mut f).unwrap();
+        assert_eq!(f, vec![
+            vec![Literal(0, true), Literal(1, false)],
+            vec![Literal(1, true), Literal(2, true), Literal(0, false)],
+            vec![Literal(2, false)],
+        ]);
+    }
+
+    #[test]
+    fn check_parse_footer() {
+        let contents = "p cnf 2 2\n1 -2 0\n2 0\n%\n0\n\n";
+        let mut f: Vec<Vec<Literal>> = Vec::new();
+        parse_cnf_into(contents.as_bytes(), &mut f).unwrap();
+        assert_eq!(f, vec![vec![Literal(0, true), Literal(1, false)], vec![Literal(1, true)]]);
+
+        let mut f: Vec<Vec<Literal>> = Vec::new();
+        assert_eq!(parse_cnf_file_into("cnfs/uf20-01.cnf", &mut f), Ok(20));
+        assert_eq!(f.len(), 91);
+        assert!(f.iter().all(|c| c.len() == 3));
+    }
+
+    #[test]
+    fn check_parse_into_errors() {
+        // Bad input is reported with the same errors as parse_dimacs rather than panicking
+        let mut f: Vec<Vec<Literal>> = Vec::new();
+        assert_eq!(parse_cnf_into("1 2 0\n".as_bytes(), &mut f), Err(ParseError::MissingHeader));
+        assert_eq!(
+            parse_cnf_into("p cnf 2 2\n1 -2 0\n2 x 0\n".as_bytes(), &mut f),
+            Err(ParseError::InvalidLiteral { line: 3, token: "x".to_string() }),
+        );
+        // Clauses before the error have already been streamed out
+        assert_eq!(f, vec![vec![Literal(0, true), Literal(1, false)]]);
+    }
 }
@@ -0,0 +1,127 @@
+use std::{sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Mutex}, thread};
+
+use crate::dpll::{solve, Literal, Solution, VarId};
+
+// Split the search space into 2^depth cubes over the depth most frequently occurring
+// variables. Every total assignment extends exactly one cube.
+pub fn generate_cubes(f: &[Vec<Literal>], num_vars: usize, depth: usize) -> Vec<Vec<Literal>> {
+    let mut occurrences = vec![0usize; num_vars];
+    for lit in f.iter().flatten() {
+        occurrences[lit.0 as usize] += 1;
+    }
+    let mut vars: Vec<VarId> = (0..num_vars as VarId).collect();
+    // Ties keep the lower numbered variable first so the cubes are deterministic
+    vars.sort_by_key(|&v| std::cmp::Reverse(occurrences[v as usize]));
+    vars.truncate(depth);
+
+    (0..1usize << vars.len())
+        .map(|bits| vars.iter().enumerate().map(|(i, &v)| Literal(v, bits >> i & 1 == 1)).collect())
+        .collect()
+}
+
+// Solve each cube of generate_cubes as unit assumptions on a pool of threads. The result is
+// Sat with the first model found, Unsat once every cube has been refuted, or Unknown if no
+// model was found and some cube could not be decided. The verdict does not depend on
+// scheduling, though which model is returned can.
+pub fn solve_cube_and_conquer(f: &[Vec<Literal>], num_vars: usize, depth: usize, threads: usize) -> Solution {
+    let cubes = generate_cubes(f, num_vars, depth);
+    let next = AtomicUsize::new(0);
+    let found = AtomicBool::new(false);
+    let undecided = AtomicBool::new(false);
+    let model = Mutex::new(None);
+
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            s.spawn(|| {
+                while !found.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(cube) = cubes.get(i) else {
+                        break;
+                    };
+
+                    let mut g = f.to_vec();
+                    g.extend(cube.iter().map(|&lit| vec![lit]));
+                    match solve(&g, num_vars) {
+                        Solution::Sat(m) => {
+                            found.store(true, Ordering::Relaxed);
+                            model.lock().unwrap().get_or_insert(m);
+                        }
+                        Solution::Unsat => (),
+                        Solution::Unknown => undecided.store(true, Ordering::Relaxed),
+                    }
+                }
+            });
+        }
+    });
+
+    match model.into_inner().unwrap() {
+        Some(m) => Solution::Sat(m),
+        None if undecided.into_inner() => Solution::Unknown,
+        None => Solution::Unsat,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{certify::{certify_sat, CertResult}, cnf_parsing::parse_cnf_file_into};
+
+    #[test]
+    fn check_generate_cubes() {
+        let c = vec![
+            vec![Literal(2, true), Literal(1, true)],
+            vec![Literal(2, false), Literal(0, true)],
+            vec![Literal(1, false)],
+        ];
+        assert_eq!(generate_cubes(&c, 3, 2), vec![
+            vec![Literal(1, false), Literal(2, false)],
+            vec![Literal(1, true), Literal(2, false)],
+            vec![Literal(1, false), Literal(2, true)],
+            vec![Literal(1, true), Literal(2, true)],
+        ]);
+        assert_eq!(generate_cubes(&c, 3, 0), vec![vec![]]);
+    }
+
+    #[test]
+    fn check_cube_and_conquer() {
+        let mut c: Vec<Vec<Literal>> = Vec::new();
+        parse_cnf_file_into("cnfs/uf20-01.cnf", &mut c).unwrap();
+        let Solution::Sat(model) = solve_cube_and_conquer(&c, 20, 3, 4) else {
+            panic!("Expected a model.");
+        };
+        assert_eq!(certify_sat(&c, &model), CertResult::Valid);
+
+        // Only the first cube, ¬0 ¬1, is satisfiable, a single thread stops there without trying the others
+        let e = vec![
+            vec![Literal(0, false)],
+            vec![Literal(1, false)],
+            vec![Literal(0, false), Literal(1, false), Literal(2, true)],
+        ];
+        assert_eq!(generate_cubes(&e, 3, 2)[0], vec![Literal(0, false), Literal(1, false)]);
+        for threads in [1, 3] {
+            assert_eq!(solve_cube_and_conquer(&e, 3, 2, threads), Solution::Sat(vec![false, false, true]));
+        }
+
+        let d: Vec<Vec<Literal>> = (0..8u32)
+            .map(|bits| (0..3).map(|v| Literal(v, bits >> v & 1 == 1)).collect())
+            .collect();
+        assert_eq!(solve(&d, 3), Solution::Unsat);
+        for threads in [1, 3] {
+            assert_eq!(solve_cube_and_conquer(&d, 3, 2, threads), Solution::Unsat);
+        }
+
+        // The single cube variable is 0, after which every variable before the last two is decided
+        // in turn, ten thousand nested decisions deep, before the cube with 0 true is found satisfiable
+        let n = 10_010;
+        let last = (n - 1) as VarId;
+        let deep = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, true), Literal(2, true)],
+            vec![Literal(0, false), Literal(3, true)],
+            vec![Literal(last - 1, true), Literal(last, true)],
+            vec![Literal(last - 1, false), Literal(last, false)],
+        ];
+        assert_eq!(generate_cubes(&deep, n, 1), vec![vec![Literal(0, false)], vec![Literal(0, true)]]);
+        assert!(matches!(solve_cube_and_conquer(&deep, n, 1, 2), Solution::Sat(_)));
+    }
+}
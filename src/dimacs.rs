@@ -0,0 +1,130 @@
+use std::{fmt::{self, Display}, io::BufRead};
+
+use crate::{cnf_parsing::ClauseSink, dpll::{Literal, VarId}};
+
+// Reasons a DIMACS CNF could not be parsed, line numbers start at 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    // No "p cnf <vars> <clauses>" line came before the first clause
+    MissingHeader,
+    // The problem line is not of the form "p cnf <vars> <clauses>"
+    InvalidHeader { line: usize },
+    // A token in a clause is not an integer
+    InvalidLiteral { line: usize, token: String },
+    // A literal names a variable beyond the count declared in the header
+    VarOutOfRange { line: usize, var: i64, num_vars: usize },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingHeader => write!(f, "missing \"p cnf\" header before the first clause"),
+            ParseError::InvalidHeader { line } => write!(f, "invalid problem line on line {line}"),
+            ParseError::InvalidLiteral { line, token } => write!(f, "invalid literal {token} on line {line}"),
+            ParseError::VarOutOfRange { line, var, num_vars } => {
+                write!(f, "literal {var} on line {line} is out of range for {num_vars} variables")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Parse "p cnf <vars> <clauses>", returning the variable count
+fn parse_header(line: &str) -> Option<usize> {
+    let mut toks = line.split_whitespace();
+    if toks.next() != Some("p") || toks.next() != Some("cnf") {
+        return None;
+    }
+    let num_vars = toks.next()?.parse().ok()?;
+    toks.next()?.parse::<usize>().ok()?;
+    if toks.next().is_some() || num_vars > VarId::MAX as usize + 1 {
+        return None;
+    }
+    Some(num_vars)
+}
+
+// Parse a DIMACS CNF, returning the declared number of variables and the clauses.
+// Clauses may span several lines and the final one may leave out its terminating 0.
+pub fn parse_dimacs(input: &str) -> Result<(usize, Vec<Vec<Literal>>), ParseError> {
+    let mut clauses = Vec::new();
+    let num_vars = parse_dimacs_into(input.as_bytes(), &mut clauses)?;
+    Ok((num_vars, clauses))
+}
+
+// Parse a DIMACS CNF as parse_dimacs does, but hand each clause to sink as soon as it is read
+// so that only the clause in progress is held in memory. Returns the declared number of variables.
+pub fn parse_dimacs_into<R: BufRead, S: ClauseSink + ?Sized>(reader: R, sink: &mut S) -> Result<usize, ParseError> {
+    let mut num_vars = None;
+    let mut clause = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.unwrap_or_else(|why| panic!("couldn't read line {}: {}", i + 1, why));
+        let line = line.trim();
+
+        // Some benchmark sets end with a "%" line followed by a lone 0, nothing after it is a clause
+        if line.starts_with('%') {
+            break;
+        }
+        if line.is_empty() || line.starts_with('c') {
+            continue;
+        }
+        if line.starts_with('p') {
+            num_vars = Some(parse_header(line).ok_or(ParseError::InvalidHeader { line: i + 1 })?);
+            continue;
+        }
+
+        let n_vars = num_vars.ok_or(ParseError::MissingHeader)?;
+        for tok in line.split_whitespace() {
+            let n: i64 = tok.parse()
+                .map_err(|_| ParseError::InvalidLiteral { line: i + 1, token: tok.to_string() })?;
+            if n == 0 {
+                sink.push_clause(&clause);
+                clause.clear();
+            } else if n.unsigned_abs() as usize > n_vars {
+                return Err(ParseError::VarOutOfRange { line: i + 1, var: n, num_vars: n_vars });
+            } else {
+                clause.push(Literal((n.unsigned_abs() - 1) as VarId, n > 0));
+            }
+        }
+    }
+
+    if !clause.is_empty() {
+        sink.push_clause(&clause);
+    }
+    num_vars.ok_or(ParseError::MissingHeader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_parse_dimacs() {
+        let contents = "c example\np cnf 3 3\n1 -2 0  \n2 3\n-1 0 -3\n";
+        assert_eq!(parse_dimacs(contents), Ok((3, vec![
+            vec![Literal(0, true), Literal(1, false)],
+            vec![Literal(1, true), Literal(2, true), Literal(0, false)],
+            vec![Literal(2, false)],
+        ])));
+
+        let (num_vars, f) = parse_dimacs(&crate::cnf_parsing::parse_cnf("cnfs/uf20-01.cnf")).unwrap();
+        assert_eq!((num_vars, f.len()), (20, 91));
+    }
+
+    #[test]
+    fn check_parse_dimacs_errors() {
+        assert_eq!(parse_dimacs("1 2 0\n"), Err(ParseError::MissingHeader));
+        assert_eq!(parse_dimacs("c nothing\n"), Err(ParseError::MissingHeader));
+        assert_eq!(parse_dimacs("p cnf x 1\n"), Err(ParseError::InvalidHeader { line: 1 }));
+        assert_eq!(
+            parse_dimacs("p cnf 2 1\n1 a 0\n"),
+            Err(ParseError::InvalidLiteral { line: 2, token: "a".to_string() }),
+        );
+        assert_eq!(
+            parse_dimacs("p cnf 2 1\n\n1 -3 0\n"),
+            Err(ParseError::VarOutOfRange { line: 3, var: -3, num_vars: 2 }),
+        );
+        assert_eq!(parse_dimacs("p cnf 2 0\n1 a 0\n").unwrap_err().to_string(), "invalid literal a on line 2");
+    }
+}
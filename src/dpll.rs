@@ -1,7 +1,12 @@
-use std::fmt::Debug;
+use std::{fmt::{self, Debug, Display}, sync::atomic::{AtomicBool, Ordering}, time::Instant};
 
-#[derive(PartialEq, Eq, Clone, Copy)]
-pub struct Literal(u16, bool);
+use crate::{cdcl::Cdcl, heuristics::{DecisionHeuristic, FirstUnassigned}, model::BitModel, preprocess::normalize};
+
+// Variables are numbered from 0, a formula over n variables uses ids 0..n
+pub type VarId = u32;
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Literal(pub VarId, pub bool);
 
 impl Debug for Literal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -12,12 +17,92 @@ impl Debug for Literal {
     }
 }
 
+impl Literal {
+    // The literal written as a one-based signed DIMACS integer, -3 being Literal(2, false)
+    pub fn from_dimacs(i: i32) -> Literal {
+        assert!(i != 0, "0 terminates a DIMACS clause and is not a literal.");
+        Literal(i.unsigned_abs() - 1, i > 0)
+    }
+
+    // Position of the literal in tables with an entry per literal, such as watch lists: 2 * var + polarity
+    #[inline(always)]
+    pub fn index(self) -> usize {
+        2 * self.0 as usize + self.1 as usize
+    }
+
+    // The literal at a position given by index
+    #[inline(always)]
+    pub fn from_index(i: usize) -> Literal {
+        Literal((i / 2) as VarId, i % 2 == 1)
+    }
+
+    pub fn to_dimacs(&self) -> i32 {
+        assert!(self.0 < i32::MAX as VarId, "Variable {} is past the largest DIMACS variable.", self.0);
+        let n = self.0 as i32 + 1;
+        if self.1 { n } else { -n }
+    }
+}
+
+// The DIMACS integer, so models can be printed in the convention they are read in
+impl Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_dimacs())
+    }
+}
+
+// Render a literal using labels[var] as the name of its variable,
+// falling back to the variable id when there is no label for it
+pub fn fmt_literal_with(lit: &Literal, labels: &[&str]) -> String {
+    let sign = if lit.1 { "" } else { "¬" };
+    match labels.get(lit.0 as usize) {
+        Some(label) => format!("{sign}{label}"),
+        None => format!("{sign}{}", lit.0),
+    }
+}
+
+#[cfg(test)]
 #[inline(always)]
 fn is_unit(c: &[Literal]) -> bool {
     c.len() == 1
 }
 
-//Propagate all units across the formula
+// How a clause stands under a partial assignment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClauseStatus {
+    // Some literal is true
+    Satisfied,
+    // Every literal is false
+    Conflict,
+    // Every literal is false apart from this unassigned one, which must be made true
+    Unit(Literal),
+    // At least two distinct literals are unassigned and none is true
+    Unresolved,
+}
+
+// Evaluate a clause under a partial assignment
+pub fn clause_status(c: &[Literal], assigns: &[Option<bool>]) -> ClauseStatus {
+    let mut unassigned: Option<Literal> = None;
+    let mut several = false;
+    for &lit in c {
+        match assigns[lit.0 as usize] {
+            Some(b) if b == lit.1 => return ClauseStatus::Satisfied,
+            Some(_) => (),
+            None => match unassigned {
+                Some(u) if u != lit => several = true,
+                _ => unassigned = Some(lit),
+            },
+        }
+    }
+    match unassigned {
+        None => ClauseStatus::Conflict,
+        Some(_) if several => ClauseStatus::Unresolved,
+        Some(lit) => ClauseStatus::Unit(lit),
+    }
+}
+
+// Propagate the units currently in the formula across it, rescanning every clause.
+// The search uses the watched literal full_unit_prop, this remains as the reference it is tested against.
+#[cfg(test)]
 fn unit_prop(var_assigns: &mut [Option<bool>], f : &mut Vec<Vec<Literal>>) -> bool {
     // Keep track of whether or not f has been changed
     let mut changed = false;
@@ -72,16 +157,207 @@ fn unit_prop(var_assigns: &mut [Option<bool>], f : &mut Vec<Vec<Literal>>) -> bo
     changed
 }
 
-// Keep propagating units until no more are left
-fn full_unit_prop(var_assigns: &mut [Option<bool>], f : &mut Vec<Vec<Literal>>) {
-    let mut changed = unit_prop(var_assigns, f);
-    while changed {
-        changed = unit_prop(var_assigns, f);
+// Clauses of at least two literals, each watched on its first two, together with an assignment
+// built up on a trail divided into decision levels. Making a literal false only visits the clauses
+// watching it, and undoing assignments never invalidates a watch, so the lists last the whole search.
+pub(crate) struct Watched {
+    pub(crate) clauses: Vec<Vec<Literal>>,
+    // Clauses watching each literal, indexed by Literal::index
+    watches: Vec<Vec<usize>>,
+    pub(crate) assigns: Vec<Option<bool>>,
+    // The decision level each variable was assigned at and the clause that forced it, if any
+    pub(crate) level: Vec<usize>,
+    pub(crate) reason: Vec<Option<usize>>,
+    // Every assigned literal in order, with where each decision level starts
+    pub(crate) trail: Vec<Literal>,
+    pub(crate) trail_lim: Vec<usize>,
+    // Position in the trail up to which propagation has run
+    qhead: usize,
+}
+
+impl Watched {
+    pub(crate) fn new(num_vars: usize) -> Self {
+        Watched {
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2 * num_vars],
+            assigns: vec![None; num_vars],
+            level: vec![0; num_vars],
+            reason: vec![None; num_vars],
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            qhead: 0,
+        }
+    }
+
+    pub(crate) fn value(&self, lit: Literal) -> Option<bool> {
+        self.assigns[lit.0 as usize].map(|b| b == lit.1)
+    }
+
+    pub(crate) fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    // Open a new decision level, the next literal enqueued is its decision
+    pub(crate) fn new_level(&mut self) {
+        self.trail_lim.push(self.trail.len());
+    }
+
+    // Store a clause of at least two literals and watch its first two
+    pub(crate) fn attach(&mut self, c: Vec<Literal>) -> usize {
+        let ci = self.clauses.len();
+        self.watches[c[0].index()].push(ci);
+        self.watches[c[1].index()].push(ci);
+        self.clauses.push(c);
+        ci
+    }
+
+    // Make lit true at the current level, returns false if it is already false
+    pub(crate) fn enqueue(&mut self, lit: Literal, reason: Option<usize>) -> bool {
+        match self.value(lit) {
+            Some(b) => b,
+            None => {
+                let v = lit.0 as usize;
+                self.assigns[v] = Some(lit.1);
+                self.level[v] = self.decision_level();
+                self.reason[v] = reason;
+                self.trail.push(lit);
+                true
+            }
+        }
+    }
+
+    // Propagate everything on the trail not yet propagated, returning a clause made false if there is one
+    pub(crate) fn propagate(&mut self) -> Option<usize> {
+        while self.qhead < self.trail.len() {
+            let lit = self.trail[self.qhead];
+            let falsified = Literal(lit.0, !lit.1);
+            self.qhead += 1;
+
+            let list = std::mem::take(&mut self.watches[falsified.index()]);
+            let mut keep = Vec::with_capacity(list.len());
+            let mut conflict = None;
+            for (i, &ci) in list.iter().enumerate() {
+                if conflict.is_some() {
+                    keep.extend_from_slice(&list[i..]);
+                    break;
+                }
+
+                // Keep the falsified watch in position 1
+                let c = &mut self.clauses[ci];
+                if c[0] == falsified {
+                    c.swap(0, 1);
+                }
+                let first = c[0];
+                if self.assigns[first.0 as usize] == Some(first.1) {
+                    keep.push(ci);
+                    continue;
+                }
+
+                let assigns = &self.assigns;
+                let replacement = (2..c.len()).find(|&k| assigns[c[k].0 as usize] != Some(!c[k].1));
+                if let Some(k) = replacement {
+                    c.swap(1, k);
+                    let watch = c[1].index();
+                    self.watches[watch].push(ci);
+                    continue;
+                }
+
+                keep.push(ci);
+                if !self.enqueue(first, Some(ci)) {
+                    conflict = Some(ci);
+                }
+            }
+            self.watches[falsified.index()] = keep;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    // Drop every clause not marked in keep, numbering the rest in their order. No assignment may
+    // have been forced by a dropped clause.
+    pub(crate) fn remove_clauses(&mut self, keep: &[bool]) {
+        let mut renumber = vec![usize::MAX; self.clauses.len()];
+        for (next, ci) in (0..self.clauses.len()).filter(|&ci| keep[ci]).enumerate() {
+            renumber[ci] = next;
+        }
+
+        let mut ci = 0;
+        self.clauses.retain(|_| {
+            ci += 1;
+            keep[ci - 1]
+        });
+        for list in &mut self.watches {
+            list.retain(|&ci| keep[ci]);
+            for ci in list.iter_mut() {
+                *ci = renumber[*ci];
+            }
+        }
+        for ci in self.reason.iter_mut().flatten() {
+            *ci = renumber[*ci];
+        }
+    }
+
+    // Undo every assignment made above the given level
+    pub(crate) fn backtrack(&mut self, level: usize) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let start = self.trail_lim[level];
+        for lit in self.trail.drain(start..) {
+            self.assigns[lit.0 as usize] = None;
+            self.reason[lit.0 as usize] = None;
+        }
+        self.trail_lim.truncate(level);
+        self.qhead = start;
+    }
+}
+
+// Find every literal implied by the unit clauses of f. Stops at the first conflict.
+fn watched_propagation(f: &[Vec<Literal>], num_vars: usize) -> Vec<Option<bool>> {
+    let mut w = Watched::new(num_vars);
+    let mut units = Vec::new();
+    for c in f {
+        match c[..] {
+            [] => (),
+            [lit] => units.push(lit),
+            _ => {
+                w.attach(c.clone());
+            }
+        }
+    }
+
+    for lit in units {
+        if !w.enqueue(lit, None) || w.propagate().is_some() {
+            break;
+        }
+    }
+    w.assigns
+}
+
+// Keep propagating units until no more are left, then remove the clauses satisfied by the
+// implied literals and the literals they make false. A conflict leaves an empty clause behind.
+pub(crate) fn full_unit_prop(var_assigns: &mut [Option<bool>], f : &mut Vec<Vec<Literal>>) {
+    // f may mention variables past the end of var_assigns so long as none of them are implied
+    let vals = watched_propagation(f, var_assigns.len().max(var_count(f)));
+    if vals.iter().all(|v| v.is_none()) {
+        return;
+    }
+
+    for (v, val) in var_assigns.iter_mut().zip(&vals) {
+        if val.is_some() {
+            *v = *val;
+        }
+    }
+    f.retain(|c| !c.iter().any(|lit| vals[lit.0 as usize] == Some(lit.1)));
+    for c in f.iter_mut() {
+        c.retain(|lit| vals[lit.0 as usize].is_none());
     }
 }
 
 // Check whether a variable has the same polarity in each occurrence
-fn is_pure(v: u16, f : &[Vec<Literal>]) -> Option<bool> {
+fn is_pure(v: VarId, f : &[Vec<Literal>]) -> Option<bool> {
     let mut seen = false;
     let mut val = false;
     for clause in f {
@@ -106,9 +382,9 @@ fn is_pure(v: u16, f : &[Vec<Literal>]) -> Option<bool> {
 
 // For each pure variable, replace all clauses containing it and
 // replace with a unit clause consisting of that variable with it's occurring polarity
-fn pure_lit_elim(var_assigns: &mut [Option<bool>], f: &mut Vec<Vec<Literal>>) {
+pub(crate) fn pure_lit_elim(var_assigns: &mut [Option<bool>], f: &mut Vec<Vec<Literal>>) {
     for (i, v) in var_assigns.iter_mut().enumerate() {
-        if let Some(b) = is_pure(i as u16, f) {
+        if let Some(b) = is_pure(i as VarId, f) {
             // Track the assignment
             *v = Some(b);
 
@@ -117,7 +393,7 @@ fn pure_lit_elim(var_assigns: &mut [Option<bool>], f: &mut Vec<Vec<Literal>>) {
             while ix < f.len() {
                 let mut del = false;
                 for lit in &f[ix] {
-                    if lit.0 == i as u16 && lit.1 == b {
+                    if lit.0 == i as VarId && lit.1 == b {
                         del = true;
                         break;
                     }
@@ -130,43 +406,763 @@ fn pure_lit_elim(var_assigns: &mut [Option<bool>], f: &mut Vec<Vec<Literal>>) {
             }
 
             // Add the unit clause
-            f.push(vec![Literal(i as u16, b)]);
+            f.push(vec![Literal(i as VarId, b)]);
+        }
+    }
+}
+
+// Counters describing how a solve went
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Stats {
+    // Number of branching decisions made
+    pub decisions: usize,
+    // Number of search tree nodes visited below the root, one per branch tried,
+    // so both the decisions and the flipped retries after backtracking
+    pub nodes: usize,
+    // Whether unit propagation and pure literal elimination decided the formula without any decisions
+    pub resolved_by_preprocessing: bool,
+    // Per variable, how many times the search assigned it in total and how many of
+    // those assignments were true and false, counted again after every backtrack
+    pub assignments: Vec<usize>,
+    pub true_assignments: Vec<usize>,
+    pub false_assignments: Vec<usize>,
+    // Number of assignments forced by unit propagation and by pure literal elimination
+    pub propagations: usize,
+    pub pure_eliminations: usize,
+    // Number of clauses simplification removed from the formula, satisfied or otherwise
+    pub clauses_deleted: usize,
+    // Number of times a decision failed and the search restored its state to try the other polarity
+    pub backtracks: usize,
+    // Which search procedure produced the result
+    pub engine: Engine,
+}
+
+// The search procedures solve can dispatch to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    #[default]
+    Dpll,
+    Cdcl,
+}
+
+impl Stats {
+    pub(crate) fn count_assignment(&mut self, lit: Literal) {
+        let v = lit.0 as usize;
+        if v >= self.assignments.len() {
+            self.assignments.resize(v + 1, 0);
+            self.true_assignments.resize(v + 1, 0);
+            self.false_assignments.resize(v + 1, 0);
+        }
+        self.assignments[v] += 1;
+        if lit.1 {
+            self.true_assignments[v] += 1;
+        } else {
+            self.false_assignments[v] += 1;
+        }
+    }
+}
+
+// When CDCL goes back to the top level to decide afresh, keeping everything it has learned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Restarts {
+    // After RESTART_INTERVAL conflicts times each term of the Luby sequence in turn
+    Luby,
+    // After any conflict at which the agility is above AGILITY_THRESHOLD. The agility is an exponential
+    // moving average of how often an assignment gives its variable the opposite of the value it last
+    // had, moved by smoothing, between 0 and 1, towards each new assignment.
+    Agile { smoothing: f64 },
+}
+
+// Options controlling which simplifications the search applies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    // Fix pure literals to their polarity at every node. Sound for deciding satisfiability,
+    // but enumeration and counting need it off since it rules out models with the other polarity
+    pub pure_literal: bool,
+    // How many nested decisions the search may make before giving up with Unknown,
+    // a bound on the work it does along any one path. usize::MAX, the default, never gives up
+    pub max_depth: usize,
+    // Formulas with at least this many clauses are handed to CDCL instead of DPLL. CDCL has no
+    // pure literal step, so pure_literal only affects DPLL. usize::MAX keeps every formula on DPLL
+    pub cdcl_threshold: usize,
+    // Decide a variable with the value it last had before a backtrack undid it, if it has had one,
+    // instead of the polarity the heuristic suggests
+    pub phase_saving: bool,
+    // CDCL periodically deletes the worse half of its learned clauses, judged by how many decision
+    // levels their literals were assigned at when learned. Clauses spanning at most this many are never deleted
+    pub core_lbd_threshold: usize,
+    // CDCL skips a restart that falls due while the trail is well above its average length at a conflict,
+    // as Glucose does, since a search assigning more than usual may be closing in on a model
+    pub block_restarts: bool,
+    pub restarts: Restarts,
+}
+
+// Below this many clauses plain DPLL finishes before clause learning pays for itself
+pub const DEFAULT_CDCL_THRESHOLD: usize = 200;
+pub const DEFAULT_CORE_LBD_THRESHOLD: usize = 2;
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            pure_literal: true,
+            max_depth: usize::MAX,
+            cdcl_threshold: DEFAULT_CDCL_THRESHOLD,
+            phase_saving: false,
+            core_lbd_threshold: DEFAULT_CORE_LBD_THRESHOLD,
+            block_restarts: false,
+            restarts: Restarts::Luby,
         }
     }
 }
 
+// The outcome of solving a formula
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solution {
+    // A satisfying assignment indexed by variable id
+    Sat(Vec<bool>),
+    Unsat,
+    // The search was stopped before it reached an answer
+    Unknown,
+}
+
+// Turn a partial assignment into a full model, unconstrained variables are set to true
+fn complete_model(var_assigns: &[Option<bool>]) -> Vec<bool> {
+    var_assigns.iter().map(|v| v.unwrap_or(true)).collect()
+}
+
+// The search works on its own tidied copy of the formula, so in debug builds every
+// model is checked against the original before it is handed out
+#[cfg(debug_assertions)]
+pub(crate) fn check_model(f: &[Vec<Literal>], model: &[bool]) {
+    use crate::certify::{certify_sat, CertResult};
+
+    if let CertResult::Invalid { clause_index } = certify_sat(f, model) {
+        panic!("The solver produced a model violating clause {clause_index}: {:?}", f[clause_index]);
+    }
+}
+
+#[cfg(not(debug_assertions))]
+#[inline(always)]
+pub(crate) fn check_model(_f: &[Vec<Literal>], _model: &[bool]) {}
+
 // Perform the DPLL algorithm on a formula in CNF
 pub fn dpll(var_assigns: &mut [Option<bool>], f : &mut Vec<Vec<Literal>>) -> bool {
-    full_unit_prop(var_assigns, f);
-    pure_lit_elim(var_assigns, f);
+    dpll_with_stats(var_assigns, f, &mut Stats::default())
+}
+
+// Perform the DPLL algorithm, recording what happened along the way in stats
+pub fn dpll_with_stats(var_assigns: &mut [Option<bool>], f : &mut Vec<Vec<Literal>>, stats: &mut Stats) -> bool {
+    dpll_with_heuristic(var_assigns, f, &mut FirstUnassigned, stats)
+}
+
+// Perform the DPLL algorithm, letting heuristic choose each decision literal
+pub fn dpll_with_heuristic(
+    var_assigns: &mut [Option<bool>],
+    f : &mut Vec<Vec<Literal>>,
+    heuristic: &mut dyn DecisionHeuristic,
+    stats: &mut Stats,
+) -> bool {
+    dpll_with_config(var_assigns, f, heuristic, stats, Config::default()) == Some(true)
+}
+
+// Perform the DPLL algorithm as configured by config, returns None if it went deeper than config.max_depth
+pub fn dpll_with_config(
+    var_assigns: &mut [Option<bool>],
+    f : &mut Vec<Vec<Literal>>,
+    heuristic: &mut dyn DecisionHeuristic,
+    stats: &mut Stats,
+    config: Config,
+) -> Option<bool> {
+    let mut search = Search::new(heuristic, stats, &|| false);
+    search.config = config;
+    let sat = search.start(var_assigns, f);
+    stats.resolved_by_preprocessing = stats.decisions == 0 && sat.is_some();
+    sat
+}
+
+// Perform the DPLL algorithm, checking cancel before every decision and returning None once it
+// is set. var_assigns is then left holding the assignment along the path the search was on,
+// under which no clause of f is false.
+pub fn dpll_cancellable(var_assigns: &mut [Option<bool>], f : &mut Vec<Vec<Literal>>, cancel: &AtomicBool) -> Option<bool> {
+    let stop = || cancel.load(Ordering::Relaxed);
+    Search::new(&mut FirstUnassigned, &mut Stats::default(), &stop).start(var_assigns, f)
+}
+
+// Perform the DPLL algorithm exactly as dpll does. The search keeps an explicit stack of decisions
+// rather than recursing, so neither overflows however many nested decisions a formula needs.
+pub fn dpll_iter(var_assigns: &mut [Option<bool>], f : &mut Vec<Vec<Literal>>) -> bool {
+    dpll(var_assigns, f)
+}
+
+// Solve a formula over num_vars variables without modifying it
+pub fn solve(f: &[Vec<Literal>], num_vars: usize) -> Solution {
+    solve_with_config(f, num_vars, Config::default())
+}
+
+// Solve a formula over num_vars variables as configured by config,
+// Unknown if the search would have to go deeper than config.max_depth
+pub fn solve_with_config(f: &[Vec<Literal>], num_vars: usize, config: Config) -> Solution {
+    solve_with_stats(f, num_vars, config).0
+}
+
+// Solve a formula over num_vars variables as configured by config, along with how the solve went
+pub fn solve_with_stats(f: &[Vec<Literal>], num_vars: usize, config: Config) -> (Solution, Stats) {
+    let mut stats = Stats::default();
+    let solution = solve_stoppable(f, num_vars, None, &|| false, config, &mut stats);
+    (solution, stats)
+}
+
+// One more than the highest variable id mentioned in f, enough to index a model of it
+pub fn var_count(f: &[Vec<Literal>]) -> usize {
+    f.iter().flatten().map(|lit| lit.0 as usize + 1).max().unwrap_or(0)
+}
+
+// Solve a formula without modifying it, over just the variables up to the highest one it mentions
+pub fn solve_formula(f: &[Vec<Literal>]) -> Solution {
+    solve(f, var_count(f))
+}
+
+// Solve a formula over num_vars variables without modifying it, letting heuristic choose each decision literal
+pub fn solve_with_heuristic(f: &[Vec<Literal>], num_vars: usize, heuristic: &mut dyn DecisionHeuristic) -> Solution {
+    solve_stoppable(f, num_vars, Some(heuristic), &|| false, Config::default(), &mut Stats::default())
+}
+
+// Solve a formula over num_vars variables, giving up with Unknown once deadline has passed
+pub fn solve_until(f: &[Vec<Literal>], num_vars: usize, deadline: Instant) -> Solution {
+    solve_stoppable(f, num_vars, None, &|| Instant::now() >= deadline, Config::default(), &mut Stats::default())
+}
 
-    if f.is_empty() {
-        return true;
+// Solve with whichever engine config picks for f, giving up with Unknown once stop returns true. heuristic
+// chooses the decisions if given, otherwise DPLL takes the first unassigned variable and CDCL the most active.
+fn solve_stoppable(
+    f: &[Vec<Literal>],
+    num_vars: usize,
+    heuristic: Option<&mut dyn DecisionHeuristic>,
+    stop: &dyn Fn() -> bool,
+    config: Config,
+    stats: &mut Stats,
+) -> Solution {
+    if f.len() >= config.cdcl_threshold {
+        let mut solver = Cdcl::new(f, num_vars).with_config(config);
+        if let Some(heuristic) = heuristic {
+            solver = solver.with_heuristic(Box::new(heuristic));
+        }
+        let solution = solver.solve_stoppable(stop);
+        stats.clone_from(solver.stats());
+        if let Solution::Sat(model) = &solution {
+            check_model(f, model);
+        }
+        return solution;
     }
 
-    if f.iter().any(|c| c.is_empty()) {
-        return false;
+    let mut g = f.to_vec();
+    let mut var_assigns = vec![None; num_vars];
+    let mut first_unassigned = FirstUnassigned;
+    let mut search = Search::new(heuristic.unwrap_or(&mut first_unassigned), stats, stop);
+    search.config = config;
+
+    let sat = search.start(&mut var_assigns, &mut g);
+    stats.resolved_by_preprocessing = stats.decisions == 0 && sat.is_some();
+    match sat {
+        Some(true) => {
+            let model = complete_model(&var_assigns);
+            check_model(f, &model);
+            Solution::Sat(model)
+        }
+        Some(false) => Solution::Unsat,
+        None => Solution::Unknown,
     }
+}
 
-    // Pick the next variable to try
-    let x = var_assigns.iter().position(|&x| x.is_none());
-    let x_v = x.expect("We have assigned all variables, yet the formula is not empty.") as u16;
-    
-    // Try with true
-    f.push(vec![Literal(x_v, true)]);
-    if dpll(var_assigns, f) {
-        return true;
+// Solve a formula, returning the model bit-packed rather than as a Vec<bool>, or None if it is unsatisfiable
+pub fn solve_packed(f: &[Vec<Literal>], num_vars: usize) -> Option<BitModel> {
+    match solve(f, num_vars) {
+        Solution::Sat(model) => Some(BitModel::from_bools(&model)),
+        _ => None,
     }
+}
 
-    // Try with false
-    f.last_mut().unwrap()[0].1 = false;
-    dpll(var_assigns, f)
+// A model together with how the search arrived at each literal in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracedModel {
+    pub model: Vec<bool>,
+    // Literals implied by unit propagation, at the top level or under decisions
+    pub forced: Vec<Literal>,
+    // The decision literals on the path to the model
+    pub decided: Vec<Literal>,
+    // For each forced variable, the true literals of the clause that forced it
+    reasons: Vec<Vec<Literal>>,
+}
+
+impl TracedModel {
+    // The decisions that, through unit propagation, forced lit to be true. A decision
+    // explains itself, and a literal forced without any decisions has an empty explanation.
+    pub fn explain(&self, lit: Literal) -> Vec<Literal> {
+        assert!(self.model[lit.0 as usize] == lit.1, "The literal {lit:?} is false in the model.");
+
+        let mut seen = vec![false; self.model.len()];
+        seen[lit.0 as usize] = true;
+        let mut stack = vec![lit];
+        while let Some(l) = stack.pop() {
+            for &r in &self.reasons[l.0 as usize] {
+                if !seen[r.0 as usize] {
+                    seen[r.0 as usize] = true;
+                    stack.push(r);
+                }
+            }
+        }
+        self.decided.iter().copied().filter(|d| seen[d.0 as usize]).collect()
+    }
+}
+
+// Solve a formula with DPLL, whatever its size, and report which literals of the model were forced
+// and which were decided. Variables fixed by pure literal elimination, or left unconstrained, appear in neither list.
+pub fn solve_traced(f: &[Vec<Literal>], num_vars: usize) -> Option<TracedModel> {
+    let mut g = f.to_vec();
+    let mut var_assigns = vec![None; num_vars];
+    let mut stats = Stats::default();
+    let mut heuristic = FirstUnassigned;
+    let mut search = Search::new(&mut heuristic, &mut stats, &|| false);
+    if search.start(&mut var_assigns, &mut g) != Some(true) {
+        return None;
+    }
+    let model = complete_model(&var_assigns);
+    check_model(f, &model);
+
+    // The search keeps the clause that forced each literal, its other literals are all false
+    let w = &search.watched;
+    let reasons = (0..num_vars)
+        .map(|v| match w.reason[v] {
+            Some(ci) => w.clauses[ci].iter().filter(|l| l.0 as usize != v).map(|l| Literal(l.0, !l.1)).collect(),
+            None => Vec::new(),
+        })
+        .collect();
+    let pick = |origin| search.trail.iter().filter(|e| e.1 == origin).map(|e| e.0).collect();
+    Some(TracedModel {
+        forced: pick(Origin::Propagated),
+        decided: pick(Origin::Decision),
+        reasons,
+        model,
+    })
+}
+
+// Solve a formula with DPLL, whatever its size, also returning the clauses implied by its first k
+// conflicts. Plain DPLL learns nothing, so each is the negation of the decisions that led to the
+// conflict, an empty one meaning the formula is contradicted without any decisions.
+pub fn solve_recording_conflicts(f: &[Vec<Literal>], num_vars: usize, k: usize) -> (Solution, Vec<Vec<Literal>>) {
+    let mut g = f.to_vec();
+    let mut var_assigns = vec![None; num_vars];
+    let mut stats = Stats::default();
+    let mut heuristic = FirstUnassigned;
+    let mut search = Search::new(&mut heuristic, &mut stats, &|| false);
+    search.max_conflicts = k;
+
+    let solution = match search.start(&mut var_assigns, &mut g) {
+        Some(true) => {
+            let model = complete_model(&var_assigns);
+            check_model(f, &model);
+            Solution::Sat(model)
+        }
+        Some(false) => Solution::Unsat,
+        None => Solution::Unknown,
+    };
+    (solution, search.conflicts)
+}
+
+// The outcome of solving a formula, saying on Unsat which clause the search last found false
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExplainedSolution {
+    Sat(Vec<bool>),
+    // The index into f, as given, of the clause made empty by the last conflict, and its literals
+    Unsat { clause: usize, conflict: Vec<Literal> },
+    Unknown,
+}
+
+// Solve a formula with DPLL, whatever its size, reporting on Unsat the original clause that emptied out
+// at the final conflict. Every literal of it is false under the assignment the search had reached at that
+// point. CDCL could end on a learned clause instead, which is no clause of f.
+pub fn solve_explained(f: &[Vec<Literal>], num_vars: usize) -> ExplainedSolution {
+    let mut g = f.to_vec();
+    let mut var_assigns = vec![None; num_vars];
+    let mut stats = Stats::default();
+    let mut heuristic = FirstUnassigned;
+    let mut search = Search::new(&mut heuristic, &mut stats, &|| false);
+
+    match search.start(&mut var_assigns, &mut g) {
+        Some(true) => {
+            let model = complete_model(&var_assigns);
+            check_model(f, &model);
+            ExplainedSolution::Sat(model)
+        }
+        Some(false) => {
+            let assigns = &search.conflict_assigns;
+            let clause = f.iter()
+                .position(|c| c.iter().all(|lit| assigns[lit.0 as usize] == Some(!lit.1)))
+                .expect("The conflict falsifies a clause of the formula.");
+            ExplainedSolution::Unsat { clause, conflict: f[clause].clone() }
+        }
+        None => ExplainedSolution::Unknown,
+    }
+}
+
+// Reasons a formula cannot be handed to the solver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveError {
+    // A literal refers to a variable outside 0..bound
+    VarOutOfRange { var: VarId, bound: usize },
+    // More variables were declared than a Literal can address
+    TooManyVars { num_vars: usize },
+}
+
+impl Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::VarOutOfRange { var, bound } => {
+                write!(f, "variable {var} is out of range for a formula with {bound} variables")
+            }
+            SolveError::TooManyVars { num_vars } => {
+                write!(f, "{num_vars} variables were declared but at most {} are supported", VarId::MAX as usize + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+// Check that the formula is something the solver can index safely
+fn validate(f: &[Vec<Literal>], num_vars: usize) -> Result<(), SolveError> {
+    if num_vars > VarId::MAX as usize + 1 {
+        return Err(SolveError::TooManyVars { num_vars });
+    }
+    for lit in f.iter().flatten() {
+        if lit.0 as usize >= num_vars {
+            return Err(SolveError::VarOutOfRange { var: lit.0, bound: num_vars });
+        }
+    }
+    Ok(())
+}
+
+// Solve a formula over num_vars variables, reporting malformed input as an error instead of panicking
+pub fn try_solve(f: &[Vec<Literal>], num_vars: usize) -> Result<Solution, SolveError> {
+    validate(f, num_vars)?;
+    Ok(solve(f, num_vars))
+}
+
+// Perform the DPLL algorithm as dpll does, first checking that var_assigns has room for every
+// variable of f so that a malformed formula is reported before the search starts rather than panicking
+pub fn try_dpll(var_assigns: &mut [Option<bool>], f : &mut Vec<Vec<Literal>>) -> Result<bool, SolveError> {
+    validate(f, var_assigns.len())?;
+    Ok(dpll(var_assigns, f))
+}
+
+// Solve after renaming every variable v to order[v], the model is translated
+// back to the original variable ids. order must be a permutation of 0..num_vars.
+pub fn solve_with_var_order(f: &[Vec<Literal>], num_vars: usize, order: &[VarId]) -> Solution {
+    assert_eq!(order.len(), num_vars, "The variable order must cover all {num_vars} variables.");
+    let mut seen = vec![false; num_vars];
+    for &v in order {
+        assert!((v as usize) < num_vars && !seen[v as usize], "The variable order is not a permutation.");
+        seen[v as usize] = true;
+    }
+
+    let renamed: Vec<Vec<Literal>> = f.iter()
+        .map(|clause| clause.iter().map(|lit| Literal(order[lit.0 as usize], lit.1)).collect())
+        .collect();
+
+    match solve(&renamed, num_vars) {
+        Solution::Sat(model) => Solution::Sat(order.iter().map(|&v| model[v as usize]).collect()),
+        res => res,
+    }
+}
+
+// Why a variable on the trail received its value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Origin {
+    // Already assigned in var_assigns when the search started
+    Given,
+    Decision,
+    Propagated,
+    Pure,
+}
+
+// A decision on the search stack, at the decision level one more than its position
+struct Frame {
+    lit: Literal,
+    // Whether lit is already the second polarity tried
+    flipped: bool,
+    // How many clauses were left unsatisfied just before lit was decided
+    open: usize,
+}
+
+// The state of the DPLL search. The clauses are watched once when it starts and the watches are
+// kept through every decision and backtrack, which only ever undo assignments on the trail.
+struct Search<'a> {
+    heuristic: &'a mut dyn DecisionHeuristic,
+    stats: &'a mut Stats,
+    // Checked before each decision, the search gives up if it returns true
+    stop: &'a dyn Fn() -> bool,
+    watched: Watched,
+    // Every assignment on the current path in the order it was made, alongside watched.trail
+    trail: Vec<(Literal, Origin)>,
+    // The decisions on the current path, the explicit stack that stands in for recursion
+    frames: Vec<Frame>,
+    // The clause implied by each of the first max_conflicts conflicts
+    conflicts: Vec<Vec<Literal>>,
+    max_conflicts: usize,
+    // The assignment at the most recent conflict, under which some clause of the formula is false
+    conflict_assigns: Vec<Option<bool>>,
+    // The value each variable had when a backtrack last unassigned it, used when phase saving
+    phases: Vec<Option<bool>>,
+    config: Config,
+}
+
+impl<'a> Search<'a> {
+    fn new(heuristic: &'a mut dyn DecisionHeuristic, stats: &'a mut Stats, stop: &'a dyn Fn() -> bool) -> Self {
+        Search {
+            heuristic,
+            stats,
+            stop,
+            watched: Watched::new(0),
+            trail: Vec::new(),
+            frames: Vec::new(),
+            conflicts: Vec::new(),
+            max_conflicts: 0,
+            conflict_assigns: Vec::new(),
+            phases: Vec::new(),
+            config: Config::default(),
+        }
+    }
+
+    // The decisions on the current path lead to a conflict, so the formula implies their negation
+    fn record_conflict(&mut self) {
+        if self.conflicts.len() < self.max_conflicts {
+            let clause = self.trail.iter()
+                .filter(|e| e.1 == Origin::Decision)
+                .map(|e| Literal(e.0.0, !e.0.1))
+                .collect();
+            self.conflicts.push(clause);
+        }
+    }
+
+    // Put every assignment made since the trail was last brought up to date onto it, returning how many there were
+    fn record(&mut self, origin: Origin) -> usize {
+        let new = &self.watched.trail[self.trail.len()..];
+        for &lit in new {
+            self.stats.count_assignment(lit);
+            self.trail.push((lit, origin));
+        }
+        new.len()
+    }
+
+    // Search from the root, tidying the formula and watching its clauses first. Variables already
+    // assigned in var_assigns keep their values, and the assignment reached is written back to it.
+    fn start(&mut self, var_assigns: &mut [Option<bool>], f : &mut Vec<Vec<Literal>>) -> Option<bool> {
+        normalize(f);
+        // f may mention variables past the end of var_assigns, they are never decided
+        self.watched = Watched::new(var_assigns.len().max(var_count(f)));
+        for (v, b) in var_assigns.iter().enumerate() {
+            if let Some(b) = *b {
+                self.watched.enqueue(Literal(v as VarId, b), None);
+            }
+        }
+        self.record(Origin::Given);
+
+        let mut conflict = false;
+        for c in f.iter() {
+            match c[..] {
+                [] => conflict = true,
+                [lit] => conflict |= !self.watched.enqueue(lit, None),
+                _ => {
+                    self.watched.attach(c.clone());
+                }
+            }
+        }
+
+        let res = if conflict {
+            self.stats.propagations += self.record(Origin::Propagated);
+            self.conflict();
+            Some(false)
+        } else {
+            self.run(var_assigns.len(), f.len())
+        };
+        var_assigns.copy_from_slice(&self.watched.assigns[..var_assigns.len()]);
+        res
+    }
+
+    // Note a conflict under the current assignment
+    fn conflict(&mut self) {
+        self.record_conflict();
+        self.conflict_assigns.clone_from(&self.watched.assigns);
+        if let Some(frame) = self.frames.last() {
+            self.heuristic.on_conflict(frame.lit);
+        }
+    }
+
+    // Fix every pure literal of the clauses not yet satisfied, as pure_lit_elim does: variables are
+    // taken in order, each judged on the clauses left once the ones before it have been fixed
+    fn assign_pure(&mut self) {
+        let w = &self.watched;
+        // The unassigned literals of each unsatisfied clause, sorted by literal
+        let mut occurs: Vec<(usize, usize)> = Vec::new();
+        for (ci, c) in w.clauses.iter().enumerate() {
+            if !c.iter().any(|&lit| w.value(lit) == Some(true)) {
+                occurs.extend(c.iter().filter(|&&lit| w.value(lit).is_none()).map(|lit| (lit.index(), ci)));
+            }
+        }
+        occurs.sort_unstable();
+        occurs.dedup();
+
+        let mut satisfied = vec![false; w.clauses.len()];
+        let mut pure = Vec::new();
+        let mut i = 0;
+        while i < occurs.len() {
+            let neg = occurs[i].0 & !1;
+            let mid = i + occurs[i..].partition_point(|e| e.0 == neg);
+            let end = mid + occurs[mid..].partition_point(|e| e.0 == neg + 1);
+            let live = |range: &[(usize, usize)]| range.iter().any(|e| !satisfied[e.1]);
+            let (neg_live, pos_live) = (live(&occurs[i..mid]), live(&occurs[mid..end]));
+            if neg_live != pos_live {
+                let range = if pos_live { mid..end } else { i..mid };
+                for e in &occurs[range] {
+                    satisfied[e.1] = true;
+                }
+                pure.push(Literal::from_index(neg + pos_live as usize));
+            }
+            i = end;
+        }
+
+        for lit in pure {
+            self.watched.enqueue(lit, None);
+        }
+        self.stats.pure_eliminations += self.record(Origin::Pure);
+    }
+
+    // The DPLL search, returns None if stop asked it to give up or it went too deep.
+    // Each pass of the loop is one node of the search tree.
+    fn run(&mut self, num_vars: usize, num_clauses: usize) -> Option<bool> {
+        let mut open = num_clauses;
+        loop {
+            let conflict = self.watched.propagate().is_some();
+            self.stats.propagations += self.record(Origin::Propagated);
+
+            if conflict {
+                self.conflict();
+            } else {
+                if self.config.pure_literal {
+                    self.assign_pure();
+                    // Nothing a pure literal makes false occurs in an unsatisfied clause
+                    self.watched.propagate();
+                }
+
+                let w = &self.watched;
+                let left = w.clauses.iter().filter(|c| !c.iter().any(|&lit| w.value(lit) == Some(true))).count();
+                self.stats.clauses_deleted += open.saturating_sub(left);
+                open = left;
+                if left == 0 {
+                    return Some(true);
+                }
+
+                if (self.stop)() || self.frames.len() >= self.config.max_depth {
+                    return None;
+                }
+
+                // Pick the next variable to try, if there is none the assignment decides what is left
+                // and, with some clause unsatisfied, this branch has failed
+                if let Some(mut x) = self.heuristic.decide(&self.watched.assigns[..num_vars], &self.watched.clauses) {
+                    if let Some(&Some(b)) = self.phases.get(x.0 as usize) {
+                        x.1 = b;
+                    }
+                    self.stats.decisions += 1;
+                    self.frames.push(Frame { lit: x, flipped: false, open });
+                    self.branch(x);
+                    continue;
+                }
+            }
+
+            // Undo decisions until one is found whose other polarity has not been tried
+            loop {
+                let level = self.frames.len();
+                let Some(frame) = self.frames.last_mut() else {
+                    return Some(false);
+                };
+                if frame.flipped {
+                    self.frames.pop();
+                    continue;
+                }
+                frame.flipped = true;
+                let x = Literal(frame.lit.0, !frame.lit.1);
+                open = frame.open;
+
+                self.stats.backtracks += 1;
+                if self.config.phase_saving {
+                    self.phases.resize(self.watched.assigns.len(), None);
+                    for &lit in &self.watched.trail[self.watched.trail_lim[level - 1]..] {
+                        self.phases[lit.0 as usize] = Some(lit.1);
+                    }
+                }
+                self.watched.backtrack(level - 1);
+                self.trail.truncate(self.watched.trail.len());
+                self.branch(x);
+                break;
+            }
+        }
+    }
+
+    // Assert the decision literal x at a new decision level
+    fn branch(&mut self, x: Literal) {
+        self.stats.nodes += 1;
+        self.watched.new_level();
+        self.watched.enqueue(x, None);
+        self.record(Origin::Decision);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn check_dimacs_literal() {
+        assert_eq!(Literal::from_dimacs(-3), Literal(2, false));
+        assert_eq!(Literal::from_dimacs(1), Literal(0, true));
+        assert_eq!(Literal(2, false).to_dimacs(), -3);
+        for i in (-300..=300).filter(|&i| i != 0) {
+            assert_eq!(Literal::from_dimacs(i).to_dimacs(), i);
+        }
+        assert_eq!(Literal::from_dimacs(65536), Literal(65535, true));
+        assert_eq!(format!("{} {}", Literal(0, true), Literal(4, false)), "1 -5");
+    }
+
+    #[test]
+    #[should_panic(expected = "not a literal")]
+    fn check_dimacs_literal_zero() {
+        Literal::from_dimacs(0);
+    }
+
+    #[test]
+    fn check_fmt_literal_with() {
+        let labels = ["sunny", "windy", "raining"];
+        assert_eq!(fmt_literal_with(&Literal(2, false), &labels), "¬raining");
+        assert_eq!(fmt_literal_with(&Literal(0, true), &labels), "sunny");
+        assert_eq!(fmt_literal_with(&Literal(3, false), &labels), "¬3");
+        assert_eq!(fmt_literal_with(&Literal(3, true), &[]), format!("{:?}", Literal(3, true)));
+    }
+
+    #[test]
+    fn check_clause_status() {
+        let c = [Literal(0, true), Literal(1, false), Literal(2, true)];
+        assert_eq!(clause_status(&c, &[None, Some(false), None]), ClauseStatus::Satisfied);
+        assert_eq!(clause_status(&c, &[Some(false), Some(true), Some(false)]), ClauseStatus::Conflict);
+        assert_eq!(clause_status(&c, &[Some(false), None, Some(false)]), ClauseStatus::Unit(Literal(1, false)));
+        assert_eq!(clause_status(&c, &[None, Some(true), None]), ClauseStatus::Unresolved);
+        assert_eq!(clause_status(&c, &[None; 3]), ClauseStatus::Unresolved);
+
+        // A repeated literal is still only one way to satisfy the clause
+        let d = [Literal(0, false), Literal(1, true), Literal(0, false)];
+        assert_eq!(clause_status(&d, &[None, Some(false)]), ClauseStatus::Unit(Literal(0, false)));
+        assert_eq!(clause_status(&[], &[None]), ClauseStatus::Conflict);
+    }
+
     #[test]
     fn check_unit() {
         let c = vec![Literal(0, false)];
@@ -266,6 +1262,30 @@ mod tests {
         assert_eq!(vs, [Some(true), Some(false), None, None]);
     }
 
+    #[test]
+    fn check_full_unit_prop_matches_rescanning() {
+        let mut rng = crate::local_search::Rng::new(7);
+        for _ in 0..500 {
+            let mut c: Vec<Vec<Literal>> = (0..rng.below(12))
+                .map(|_| (0..1 + rng.below(3)).map(|_| Literal(rng.below(6) as VarId, rng.below(2) == 1)).collect())
+                .collect();
+            let mut d = c.clone();
+
+            let mut expected = [None; 6];
+            while unit_prop(&mut expected, &mut d) {}
+            let mut vs = [None; 6];
+            full_unit_prop(&mut vs, &mut c);
+
+            // After a conflict the rescanning version carries on regardless, so only the conflict itself must agree
+            if d.iter().any(|c| c.is_empty()) {
+                assert!(c.iter().any(|c| c.is_empty()), "{d:?} has a conflict but {c:?} does not");
+            } else {
+                assert_eq!(c, d);
+                assert_eq!(vs, expected);
+            }
+        }
+    }
+
     #[test]
     fn check_pure() {
         let c = [vec![Literal(0, true)], vec![Literal(1, false), Literal(0, false)]];
@@ -297,14 +1317,20 @@ mod tests {
 
     #[test]
     fn check_dpll_simple() {
-        let mut c = vec![
+        let c = vec![
             vec![Literal(0, true), Literal(1, true), Literal(2, true)],
             vec![Literal(0, false), Literal(1, true), Literal(2, false)],
             vec![Literal(1, false), Literal(2, true)],
         ];
         let mut vs = [None; 3];
-        assert!(dpll(&mut vs, &mut c));
+        assert!(dpll(&mut vs, &mut c.clone()));
         assert_eq!(vs, [Some(true), Some(true), Some(true)]);
+
+        // The search needs a decision, after which the rest follows by propagation
+        let (solution, stats) = solve_with_stats(&c, 3, Config::default());
+        assert!(matches!(solution, Solution::Sat(_)));
+        assert!(stats.decisions >= 1);
+        assert!(stats.propagations > 0);
     }
 
     #[test]
@@ -324,4 +1350,650 @@ mod tests {
         assert!(dpll(&mut vs, &mut c));
         //assert_eq!(vs, [Some(true), Some(true), Some(true)]);
     }
+
+    #[test]
+    fn check_dpll_iter_simple() {
+        let mut c = vec![
+            vec![Literal(0, true), Literal(1, true), Literal(2, true)],
+            vec![Literal(0, false), Literal(1, true), Literal(2, false)],
+            vec![Literal(1, false), Literal(2, true)],
+        ];
+        let mut vs = [None; 3];
+        assert!(dpll_iter(&mut vs, &mut c));
+        assert_eq!(vs, [Some(true), Some(true), Some(true)]);
+    }
+
+    #[test]
+    fn check_dpll_iter_hard() {
+        let mut c = vec![
+            vec![Literal(0, true), Literal(3, true)],
+            vec![Literal(0, true), Literal(2, false), Literal(5, false)],
+            vec![Literal(0, true), Literal(5, true), Literal(9, true)],
+            vec![Literal(1, true), Literal(8, true)],
+            vec![Literal(4, false), Literal(2, false), Literal(6, true)],
+            vec![Literal(4, false), Literal(5, true), Literal(6, false)],
+            vec![Literal(4, true), Literal(5, true), Literal(7, false)],
+            vec![Literal(4, true), Literal(7, true), Literal(9, false)],
+        ];
+        let mut vs = [None; 10];
+
+        assert!(dpll_iter(&mut vs, &mut c));
+    }
+
+    // Satisfiability by trying both values of each variable in turn, backing out as soon as a clause is
+    // false. It shares nothing with Search, so the two can be checked against each other.
+    fn reference_sat(f: &[Vec<Literal>], assigns: &mut Vec<Option<bool>>, v: usize) -> bool {
+        let falsified = f.iter().any(|c| c.iter().all(|lit| assigns[lit.0 as usize] == Some(!lit.1)));
+        if falsified || v == assigns.len() {
+            return !falsified;
+        }
+        for b in [true, false] {
+            assigns[v] = Some(b);
+            if reference_sat(f, assigns, v + 1) {
+                return true;
+            }
+        }
+        assigns[v] = None;
+        false
+    }
+
+    #[test]
+    fn check_dpll_iter_matches_reference() {
+        let unsat: Vec<Vec<Literal>> = (0..8u32)
+            .map(|bits| (0..3).map(|v| Literal(v, bits >> v & 1 == 1)).collect())
+            .collect();
+        let mut formulas = vec![(unsat, 3)];
+        for name in ["uf20-01", "uf20-010", "uf20-0100"] {
+            let mut f: Vec<Vec<Literal>> = Vec::new();
+            crate::cnf_parsing::parse_cnf_file_into(&format!("cnfs/{name}.cnf"), &mut f).unwrap();
+            formulas.push((f, 20));
+        }
+        // Random 3-CNF around the satisfiability threshold, so both verdicts come up
+        let mut rng = crate::local_search::Rng::new(11);
+        for _ in 0..100 {
+            let f = (0..43)
+                .map(|_| (0..3).map(|_| Literal(rng.below(10) as VarId, rng.below(2) == 1)).collect())
+                .collect();
+            formulas.push((f, 10));
+        }
+
+        for (c, n) in formulas {
+            let mut vs = vec![None; n];
+            let sat = dpll_iter(&mut vs, &mut c.clone());
+            assert_eq!(sat, reference_sat(&c, &mut vec![None; n], 0), "{c:?}");
+            if sat {
+                assert_eq!(crate::certify::certify_sat(&c, &complete_model(&vs)), crate::certify::CertResult::Valid);
+            }
+        }
+    }
+
+    #[test]
+    fn check_dpll_iter_deep() {
+        // Only the last two variables are constrained, and neither is pure, so every
+        // variable before them is decided in turn, one nesting level each
+        const N: usize = 50_000;
+        let last = (N - 1) as VarId;
+        let c = vec![
+            vec![Literal(last - 1, true), Literal(last, true)],
+            vec![Literal(last - 1, false), Literal(last, false)],
+        ];
+
+        // A stack far too small for a search recursing once per decision to get that deep
+        let vs = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(move || {
+                let mut vs = vec![None; N];
+                assert!(dpll_iter(&mut vs, &mut c.clone()));
+                vs
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(vs[..N - 1].iter().all(|&v| v == Some(true)));
+        assert_eq!(vs[N - 1], Some(false));
+    }
+
+    #[test]
+    fn check_deep_search() {
+        // As in check_dpll_iter_deep, every variable before the last two is decided in turn,
+        // ten thousand nested decisions that every entry point goes through by default
+        let n = 10_005;
+        let last = (n - 1) as VarId;
+        let c = vec![
+            vec![Literal(last - 1, true), Literal(last, true)],
+            vec![Literal(last - 1, false), Literal(last, false)],
+        ];
+        let model: Vec<bool> = vec![true; n - 1].into_iter().chain([false]).collect();
+
+        let mut stats = Stats::default();
+        assert!(dpll_with_stats(&mut vec![None; n], &mut c.clone(), &mut stats));
+        assert_eq!(stats.decisions, n - 1);
+        assert!(dpll(&mut vec![None; n], &mut c.clone()));
+        assert_eq!(solve(&c, n), Solution::Sat(model.clone()));
+        assert_eq!(solve_with_heuristic(&c, n, &mut FirstUnassigned), Solution::Sat(model.clone()));
+        assert_eq!(crate::solver::Solver::new(c.clone(), n).solve(), Solution::Sat(model));
+
+        // Only a limit asked for cuts it short
+        let shallow = Config { max_depth: 100, ..Config::default() };
+        assert_eq!(solve_with_config(&c, n, shallow), Solution::Unknown);
+    }
+
+    #[test]
+    fn check_dpll_stats_preprocessing() {
+        let mut c = vec![
+            vec![Literal(0, true)],
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(1, false), Literal(2, false)],
+        ];
+        let mut vs = [None; 3];
+        let mut stats = Stats::default();
+
+        assert!(dpll_with_stats(&mut vs, &mut c, &mut stats));
+        assert_eq!(vs, [Some(true), Some(true), Some(false)]);
+        assert_eq!(stats.decisions, 0);
+        assert!(stats.resolved_by_preprocessing);
+    }
+
+    #[test]
+    fn check_dpll_stats_search() {
+        let mut c = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, false), Literal(1, false)],
+        ];
+        let mut vs = [None; 2];
+        let mut stats = Stats::default();
+
+        assert!(dpll_with_stats(&mut vs, &mut c, &mut stats));
+        assert!(stats.decisions > 0);
+        assert!(!stats.resolved_by_preprocessing);
+    }
+
+    #[test]
+    fn check_solve_formula() {
+        // Variable 1 never appears, so it is filled in as true
+        let c = vec![
+            vec![Literal(0, false), Literal(2, false)],
+            vec![Literal(2, true)],
+            vec![Literal(3, false), Literal(0, true)],
+        ];
+        let orig = c.clone();
+        assert_eq!(var_count(&c), 4);
+        assert_eq!(solve_formula(&c), Solution::Sat(vec![false, true, true, false]));
+        assert_eq!(solve_formula(&c), solve_formula(&c));
+        assert_eq!(c, orig);
+
+        assert_eq!(solve_formula(&[vec![Literal(0, true)], vec![Literal(0, false)]]), Solution::Unsat);
+        assert_eq!(solve_formula(&[]), Solution::Sat(vec![]));
+    }
+
+    #[test]
+    fn check_dpll_cancellable() {
+        // Nine pigeons in eight holes, far beyond what plain DPLL finishes
+        let var = |p: VarId, h: VarId| p * 8 + h;
+        let mut c: Vec<Vec<Literal>> = (0..9).map(|p| (0..8).map(|h| Literal(var(p, h), true)).collect()).collect();
+        for h in 0..8 {
+            for p in 0..9 {
+                for q in p + 1..9 {
+                    c.push(vec![Literal(var(p, h), false), Literal(var(q, h), false)]);
+                }
+            }
+        }
+
+        let cancel = AtomicBool::new(false);
+        let mut vs = vec![None; 72];
+        let start = Instant::now();
+        let res = std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                cancel.store(true, Ordering::Relaxed);
+            });
+            dpll_cancellable(&mut vs, &mut c.clone(), &cancel)
+        });
+        assert_eq!(res, None);
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+
+        // The search stopped somewhere below the root without breaking any clause
+        assert!(vs.iter().any(|v| v.is_some()));
+        assert!(c.iter().all(|clause| clause.iter().any(|lit| vs[lit.0 as usize] != Some(!lit.1))));
+
+        let d = vec![vec![Literal(0, true), Literal(1, true)]];
+        assert_eq!(dpll_cancellable(&mut [None; 2], &mut d.clone(), &AtomicBool::new(false)), Some(true));
+    }
+
+    #[test]
+    fn check_solve_until() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, false), Literal(1, false)],
+        ];
+
+        // A deadline that has already passed stops at the first decision
+        assert_eq!(solve_until(&c, 2, Instant::now()), Solution::Unknown);
+
+        let later = Instant::now() + std::time::Duration::from_secs(60);
+        assert_eq!(solve_until(&c, 2, later), Solution::Sat(vec![true, false]));
+
+        let d = vec![vec![Literal(0, true)], vec![Literal(0, false)]];
+        assert_eq!(solve_until(&d, 1, later), Solution::Unsat);
+    }
+
+    #[test]
+    fn check_dpll_backtrack_restores() {
+        // The true branch for variable 0 fails, the false branch must see the original clauses
+        let mut c = vec![
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(0, false), Literal(1, false)],
+            vec![Literal(0, true), Literal(2, true)],
+            vec![Literal(0, true), Literal(2, false), Literal(1, true)],
+        ];
+        let mut vs = [None; 3];
+        assert!(dpll(&mut vs, &mut c));
+        assert_eq!(vs, [Some(false), Some(true), Some(true)]);
+    }
+
+    #[test]
+    fn check_solve_with_var_order() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true), Literal(2, true)],
+            vec![Literal(0, false), Literal(1, true), Literal(2, false)],
+            vec![Literal(1, false), Literal(2, true)],
+            vec![Literal(0, false), Literal(2, false)],
+        ];
+        let order = [2, 0, 1];
+
+        let Solution::Sat(model) = solve_with_var_order(&c, 3, &order) else {
+            panic!("Expected a model.");
+        };
+        assert!(matches!(solve(&c, 3), Solution::Sat(_)));
+        assert!(c.iter().all(|clause| clause.iter().any(|lit| model[lit.0 as usize] == lit.1)));
+
+        let d = vec![vec![Literal(0, true)], vec![Literal(1, true)], vec![Literal(0, false), Literal(1, false)]];
+        assert_eq!(solve_with_var_order(&d, 2, &[1, 0]), Solution::Unsat);
+        assert_eq!(solve(&d, 2), Solution::Unsat);
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_solve_with_bad_var_order() {
+        let c = vec![vec![Literal(0, true), Literal(1, true)]];
+        solve_with_var_order(&c, 2, &[1, 1]);
+    }
+
+    #[test]
+    fn check_try_solve() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, true)],
+            vec![Literal(0, true)],
+            vec![],
+        ];
+        assert_eq!(try_solve(&c, 2), Ok(Solution::Unsat));
+        assert_eq!(try_solve(&c[..3], 2), Ok(Solution::Sat(vec![true, true])));
+        assert_eq!(try_solve(&[], 0), Ok(Solution::Sat(vec![])));
+    }
+
+    #[test]
+    fn check_try_solve_out_of_range() {
+        let c = vec![vec![Literal(0, true), Literal(4, false)]];
+        assert_eq!(try_solve(&c, 3), Err(SolveError::VarOutOfRange { var: 4, bound: 3 }));
+    }
+
+    #[test]
+    fn check_try_solve_too_many_vars() {
+        let c = vec![vec![Literal(0, true)]];
+        let n = VarId::MAX as usize + 2;
+        assert_eq!(try_solve(&c, n), Err(SolveError::TooManyVars { num_vars: n }));
+    }
+
+    #[test]
+    fn check_try_dpll() {
+        let mut c = vec![vec![Literal(0, true), Literal(1, false)], vec![Literal(5, true)]];
+        let mut vs = [None; 3];
+        assert_eq!(try_dpll(&mut vs, &mut c), Err(SolveError::VarOutOfRange { var: 5, bound: 3 }));
+        // Nothing was touched
+        assert_eq!(vs, [None; 3]);
+        assert_eq!(c.len(), 2);
+
+        let mut vs = [None; 6];
+        assert_eq!(try_dpll(&mut vs, &mut c), Ok(true));
+        assert_eq!(vs[5], Some(true));
+        let mut d = vec![vec![Literal(2, true)], vec![Literal(2, false)]];
+        assert_eq!(try_dpll(&mut [None; 3], &mut d), Ok(false));
+    }
+
+    #[test]
+    fn check_solve_past_u16_vars() {
+        // A chain of implications x0 -> x1 -> ... -> xn with x0 forced, reaching past
+        // the old 16 bit limit. Propagation alone fixes every variable.
+        let n: VarId = 70_123;
+        let mut c: Vec<Vec<Literal>> = (0..n).map(|v| vec![Literal(v, false), Literal(v + 1, true)]).collect();
+        c.push(vec![Literal(0, true)]);
+        assert_eq!(crate::cdcl::solve_cdcl(&c, n as usize + 1), Solution::Sat(vec![true; n as usize + 1]));
+
+        c.push(vec![Literal(n, false)]);
+        assert_eq!(crate::cdcl::solve_cdcl(&c, n as usize + 1), Solution::Unsat);
+        assert_eq!(Literal::from_dimacs(70_124), Literal(n, true));
+        assert_eq!(Literal(n, false).to_dimacs(), -70_124);
+    }
+
+    #[test]
+    fn check_solve_traced() {
+        let c = vec![
+            vec![Literal(0, true)],
+            vec![Literal(0, false), Literal(1, false)],
+            vec![Literal(2, true), Literal(3, true)],
+            vec![Literal(2, false), Literal(3, false)],
+            vec![Literal(2, true), Literal(1, true), Literal(3, false)],
+        ];
+        let t = solve_traced(&c, 4).unwrap();
+
+        assert_eq!(t.model, vec![true, false, true, false]);
+        assert_eq!(t.forced, vec![Literal(0, true), Literal(1, false), Literal(3, false)]);
+        assert_eq!(t.decided, vec![Literal(2, true)]);
+
+        let d = vec![vec![Literal(0, true)], vec![Literal(0, false)]];
+        assert_eq!(solve_traced(&d, 1), None);
+    }
+
+    #[test]
+    fn check_explain() {
+        let c = vec![
+            // Deciding 0 forces 1 and then 2
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(1, false), Literal(2, true)],
+            vec![Literal(0, true), Literal(2, false)],
+            // Deciding 3 forces ¬4
+            vec![Literal(3, true), Literal(4, true)],
+            vec![Literal(3, false), Literal(4, false)],
+            // 6 is forced before any decision
+            vec![Literal(5, true)],
+            vec![Literal(5, false), Literal(6, true)],
+        ];
+        let t = solve_traced(&c, 7).unwrap();
+        assert_eq!(t.decided, vec![Literal(0, true), Literal(3, true)]);
+
+        assert_eq!(t.explain(Literal(2, true)), vec![Literal(0, true)]);
+        assert_eq!(t.explain(Literal(4, false)), vec![Literal(3, true)]);
+        assert_eq!(t.explain(Literal(0, true)), vec![Literal(0, true)]);
+        assert_eq!(t.explain(Literal(6, true)), vec![]);
+    }
+
+    #[test]
+    fn check_solve_packed() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true), Literal(2, true)],
+            vec![Literal(0, false), Literal(1, true), Literal(2, false)],
+            vec![Literal(1, false), Literal(2, true)],
+        ];
+        let Solution::Sat(model) = solve(&c, 3) else {
+            panic!("Expected a model.");
+        };
+        let packed = solve_packed(&c, 3).unwrap();
+        assert_eq!(packed.len(), model.len());
+        for (v, &b) in model.iter().enumerate() {
+            assert_eq!(packed.get(v), b);
+        }
+
+        let d = vec![vec![Literal(0, true)], vec![Literal(0, false)]];
+        assert_eq!(solve_packed(&d, 1), None);
+    }
+
+    // Solve, then corrupt the model before it is checked
+    #[cfg(debug_assertions)]
+    fn solve_broken(f: &[Vec<Literal>], num_vars: usize) -> Vec<bool> {
+        let Solution::Sat(mut model) = solve(f, num_vars) else {
+            panic!("Expected a model.");
+        };
+        for b in model.iter_mut() {
+            *b = !*b;
+        }
+        check_model(f, &model);
+        model
+    }
+
+    #[test]
+    fn check_model_checked() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, false), Literal(2, true)],
+        ];
+        assert!(matches!(solve(&c, 3), Solution::Sat(_)));
+        assert!(solve_traced(&c, 3).is_some());
+        assert!(solve_packed(&c, 3).is_some());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "violating clause")]
+    fn check_model_checked_broken() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, false), Literal(2, true)],
+        ];
+        solve_broken(&c, 3);
+    }
+
+    // Branch on the unassigned variables in a fixed order
+    struct InOrder(Vec<VarId>);
+
+    impl DecisionHeuristic for InOrder {
+        fn decide(&mut self, var_assigns: &[Option<bool>], _f: &[Vec<Literal>]) -> Option<Literal> {
+            let x = self.0.iter().find(|&&v| var_assigns[v as usize].is_none())?;
+            Some(Literal(*x, true))
+        }
+    }
+
+    #[test]
+    fn check_stats_nodes() {
+        // An equivalence chain over 0..5 that is irrelevant to the contradiction on 5 and 6
+        let mut c = vec![
+            vec![Literal(5, true), Literal(6, true)],
+            vec![Literal(5, true), Literal(6, false)],
+            vec![Literal(5, false), Literal(6, true)],
+            vec![Literal(5, false), Literal(6, false)],
+        ];
+        for i in 0..4 {
+            c.push(vec![Literal(i, true), Literal(i + 1, false)]);
+            c.push(vec![Literal(i, false), Literal(i + 1, true)]);
+        }
+
+        let mut naive = Stats::default();
+        assert!(!dpll_with_stats(&mut [None; 7], &mut c.clone(), &mut naive));
+
+        let mut better = Stats::default();
+        let mut h = InOrder(vec![5, 6, 0, 1, 2, 3, 4]);
+        assert!(!dpll_with_heuristic(&mut [None; 7], &mut c.clone(), &mut h, &mut better));
+
+        assert_eq!(better.nodes, 2);
+        assert!(better.nodes < naive.nodes, "{} vs {}", better.nodes, naive.nodes);
+    }
+
+    #[test]
+    fn check_stats_polarity() {
+        // The same shape as check_stats_nodes: a chain over 0..5 and a contradiction on 5 and 6
+        let mut c = vec![
+            vec![Literal(5, true), Literal(6, true)],
+            vec![Literal(5, true), Literal(6, false)],
+            vec![Literal(5, false), Literal(6, true)],
+            vec![Literal(5, false), Literal(6, false)],
+        ];
+        for i in 0..4 {
+            c.push(vec![Literal(i, true), Literal(i + 1, false)]);
+            c.push(vec![Literal(i, false), Literal(i + 1, true)]);
+        }
+        let mut stats = Stats::default();
+        assert!(!dpll_with_stats(&mut [None; 7], &mut c, &mut stats));
+
+        for v in 0..stats.assignments.len() {
+            assert_eq!(stats.true_assignments[v] + stats.false_assignments[v], stats.assignments[v]);
+        }
+        // 0 is decided both ways, dragging the chain with it, and 5 is tried both ways under each
+        assert_eq!((stats.true_assignments[0], stats.false_assignments[0]), (1, 1));
+        assert_eq!((stats.true_assignments[4], stats.false_assignments[4]), (1, 1));
+        assert_eq!((stats.true_assignments[5], stats.false_assignments[5]), (2, 2));
+    }
+
+    #[test]
+    fn check_solve_recording_conflicts() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true), Literal(2, true)],
+            vec![Literal(0, true), Literal(1, false), Literal(2, false)],
+            vec![Literal(0, false), Literal(1, true), Literal(2, false)],
+            vec![Literal(0, false), Literal(1, false), Literal(2, true)],
+            vec![Literal(0, true), Literal(1, true), Literal(2, false)],
+            vec![Literal(0, false), Literal(1, false), Literal(2, false)],
+            vec![Literal(0, true), Literal(1, false), Literal(2, true)],
+            vec![Literal(0, false), Literal(1, true), Literal(2, true)],
+        ];
+        let (solution, conflicts) = solve_recording_conflicts(&c, 3, 2);
+        assert_eq!(solution, Solution::Unsat);
+        assert_eq!(conflicts.len(), 2);
+
+        // Each recorded clause really is implied: the formula plus its negation is unsatisfiable
+        for clause in &conflicts {
+            assert!(!clause.is_empty());
+            let mut g = c.clone();
+            g.extend(clause.iter().map(|lit| vec![Literal(lit.0, !lit.1)]));
+            assert_eq!(solve(&g, 3), Solution::Unsat);
+        }
+
+        let (_, conflicts) = solve_recording_conflicts(&c, 3, 100);
+        assert!(conflicts.len() > 2);
+    }
+
+    #[test]
+    fn check_solve_explained() {
+        let c = vec![vec![Literal(0, true)], vec![Literal(0, false)]];
+        assert_eq!(solve_explained(&c, 1), ExplainedSolution::Unsat { clause: 1, conflict: vec![Literal(0, false)] });
+
+        // Refuted only after branching, the reported clause is one of the original eight
+        let d: Vec<Vec<Literal>> = (0..8u32)
+            .map(|bits| (0..3).map(|v| Literal(v, bits >> v & 1 == 1)).collect())
+            .collect();
+        let ExplainedSolution::Unsat { clause, conflict } = solve_explained(&d, 3) else {
+            panic!("Expected Unsat.");
+        };
+        assert_eq!(conflict, d[clause]);
+
+        assert_eq!(solve_explained(&[vec![Literal(1, true)], vec![]], 2), ExplainedSolution::Unsat { clause: 1, conflict: vec![] });
+        assert_eq!(solve_explained(&c[..1], 2), ExplainedSolution::Sat(vec![true, true]));
+    }
+
+    // Branch on the first unassigned variable, trying false first
+    struct FalseFirst;
+
+    impl DecisionHeuristic for FalseFirst {
+        fn decide(&mut self, var_assigns: &[Option<bool>], _f: &[Vec<Literal>]) -> Option<Literal> {
+            let x = var_assigns.iter().position(|&x| x.is_none())?;
+            Some(Literal(x as VarId, false))
+        }
+    }
+
+    #[test]
+    fn check_pure_literal_disabled() {
+        // Both variables are pure, so elimination forces 0 true before anything is decided
+        let c = vec![vec![Literal(0, true), Literal(1, true)]];
+        let mut vs = [None; 2];
+        assert!(dpll_with_heuristic(&mut vs, &mut c.clone(), &mut FalseFirst, &mut Stats::default()));
+        assert_eq!(vs[0], Some(true));
+
+        // Without it the search is free to pick the other polarity of 0
+        let config = Config { pure_literal: false, ..Config::default() };
+        let mut vs = [None; 2];
+        assert_eq!(dpll_with_config(&mut vs, &mut c.clone(), &mut FalseFirst, &mut Stats::default(), config), Some(true));
+        assert_eq!(vs, [Some(false), Some(true)]);
+    }
+
+    #[test]
+    fn check_max_depth() {
+        // Every clause over 3 variables, refuting it takes two nested decisions
+        let c: Vec<Vec<Literal>> = (0..8u32)
+            .map(|bits| (0..3).map(|v| Literal(v, bits >> v & 1 == 1)).collect())
+            .collect();
+        let shallow = Config { max_depth: 1, ..Config::default() };
+        assert_eq!(solve_with_config(&c, 3, shallow), Solution::Unknown);
+        assert_eq!(solve_with_config(&c, 3, Config { max_depth: 2, ..shallow }), Solution::Unsat);
+        assert_eq!(solve_with_config(&c, 3, Config::default()), Solution::Unsat);
+
+        // Formulas settled without a decision never hit the limit
+        let d = vec![vec![Literal(0, true)], vec![Literal(0, false), Literal(1, true)]];
+        let none = Config { max_depth: 0, ..Config::default() };
+        assert_eq!(solve_with_config(&d, 2, none), Solution::Sat(vec![true, true]));
+    }
+
+    #[test]
+    fn check_phase_saving() {
+        // Every clause over the three variables after v, each weakened by ¬v, so that v true
+        // takes a few decisions to refute
+        let refuted_by = |v: VarId| (0..8u32).map(move |bits| {
+            let mut c: Vec<Literal> = (1..4).map(|k| Literal(v + k, bits >> (k - 1) & 1 == 1)).collect();
+            c.push(Literal(v, false));
+            c
+        });
+        // Deciding 0 true fails, but first propagates every x = 4 + 4i false. Each x true then
+        // fails again once 0 is false, while false is the value it had before the backtrack.
+        let mut c: Vec<Vec<Literal>> = refuted_by(0).collect();
+        for i in 0..10 {
+            let x = 4 + 4 * i;
+            c.push(vec![Literal(0, false), Literal(x, false)]);
+            c.extend(refuted_by(x));
+        }
+
+        // CDCL saves phases over its backjumps just the same
+        for cdcl_threshold in [usize::MAX, 0] {
+            let naive = Config { pure_literal: false, cdcl_threshold, ..Config::default() };
+            let saving = Config { phase_saving: true, ..naive };
+            let (solution, naive) = solve_with_stats(&c, 44, naive);
+            assert!(matches!(solution, Solution::Sat(_)));
+            let (solution, saving) = solve_with_stats(&c, 44, saving);
+            let Solution::Sat(model) = solution else {
+                panic!("Expected a model.");
+            };
+            assert!((0..10).all(|i| !model[4 + 4 * i]));
+            assert!(saving.decisions < naive.decisions, "{} vs {}", saving.decisions, naive.decisions);
+            assert!(saving.backtracks < naive.backtracks);
+        }
+    }
+
+    #[test]
+    fn check_engine_dispatch() {
+        let tiny = vec![vec![Literal(0, true), Literal(1, true)], vec![Literal(0, false)]];
+        let (solution, stats) = solve_with_stats(&tiny, 2, Config::default());
+        assert_eq!(solution, Solution::Sat(vec![false, true]));
+        assert_eq!(stats.engine, Engine::Dpll);
+
+        // A chain of implications x0 -> x1 -> ... -> xn with x0 forced, past the threshold
+        let n = DEFAULT_CDCL_THRESHOLD as VarId;
+        let mut chain: Vec<Vec<Literal>> = (0..n).map(|v| vec![Literal(v, false), Literal(v + 1, true)]).collect();
+        chain.push(vec![Literal(0, true)]);
+        let (solution, stats) = solve_with_stats(&chain, n as usize + 1, Config::default());
+        assert_eq!(solution, Solution::Sat(vec![true; n as usize + 1]));
+        assert_eq!(stats.engine, Engine::Cdcl);
+        assert_eq!((stats.decisions, stats.propagations), (0, n as usize + 1));
+        assert_eq!(stats.true_assignments, vec![1; n as usize + 1]);
+        assert!(stats.resolved_by_preprocessing);
+
+        // Without x0 forced it takes a decision, so the depth limit and the deadline both stop it
+        let open = &chain[..n as usize];
+        let (solution, stats) = solve_with_stats(open, n as usize + 1, Config::default());
+        assert_eq!(solution, Solution::Sat(vec![true; n as usize + 1]));
+        assert_eq!((stats.engine, stats.decisions, stats.nodes, stats.propagations), (Engine::Cdcl, 1, 1, n as usize));
+        assert!(!stats.resolved_by_preprocessing);
+        let shallow = Config { max_depth: 0, ..Config::default() };
+        assert_eq!(solve_with_stats(open, n as usize + 1, shallow), (Solution::Unknown, Stats { engine: Engine::Cdcl, ..Stats::default() }));
+        assert_eq!(solve_until(open, n as usize + 1, Instant::now()), Solution::Unknown);
+        assert_eq!(solve_with_heuristic(open, n as usize + 1, &mut FirstUnassigned), Solution::Sat(vec![true; n as usize + 1]));
+        assert_eq!(solve_packed(open, n as usize + 1), Some(BitModel::from_bools(&vec![true; n as usize + 1])));
+
+        chain.push(vec![Literal(n, false)]);
+        let (solution, stats) = solve_with_stats(&chain, n as usize + 1, Config::default());
+        assert_eq!(solution, Solution::Unsat);
+        assert_eq!(stats.engine, Engine::Cdcl);
+
+        // The threshold can keep it on DPLL
+        let config = Config { cdcl_threshold: usize::MAX, ..Config::default() };
+        let (solution, stats) = solve_with_stats(&chain, n as usize + 1, config);
+        assert_eq!(solution, Solution::Unsat);
+        assert_eq!(stats.engine, Engine::Dpll);
+    }
 }
@@ -0,0 +1,362 @@
+use crate::dpll::{solve, Literal, Solution, VarId};
+
+// Encode a boolean function of num_inputs variables given by its true rows as CNF.
+// Row r assigns variable i the value of bit i of r, every row missing from
+// minterms_true gets a clause (a maxterm) ruling it out.
+pub fn from_truth_table(num_inputs: usize, minterms_true: &[u32]) -> Vec<Vec<Literal>> {
+    assert!(num_inputs < 32, "A truth table over {num_inputs} inputs does not fit in u32 rows.");
+    (0..1u32 << num_inputs)
+        .filter(|row| !minterms_true.contains(row))
+        .map(|row| (0..num_inputs).map(|i| Literal(i as VarId, row >> i & 1 == 0)).collect())
+        .collect()
+}
+
+#[inline(always)]
+fn neg(lit: Literal) -> Literal {
+    Literal(lit.0, !lit.1)
+}
+
+// Sequential counter (Sinz) encoding of "at most k of lits are true", auxiliary
+// variables are allocated from next_var upwards. s[i][j] means at least j + 1
+// of the first i + 1 literals are true. Uses O(n·k) clauses and auxiliary variables.
+pub fn sequential_at_most_k(lits: &[Literal], k: usize, next_var: &mut VarId) -> Vec<Vec<Literal>> {
+    let n = lits.len();
+    if n <= k {
+        return Vec::new();
+    }
+    if k == 0 {
+        return lits.iter().map(|&x| vec![neg(x)]).collect();
+    }
+
+    let mut s = Vec::with_capacity(n - 1);
+    for _ in 0..n - 1 {
+        s.push((0..k).map(|j| Literal(*next_var + j as VarId, true)).collect::<Vec<_>>());
+        *next_var += k as VarId;
+    }
+
+    let mut clauses = vec![vec![neg(lits[0]), s[0][0]]];
+    for &s0j in &s[0][1..] {
+        clauses.push(vec![neg(s0j)]);
+    }
+    for i in 1..n - 1 {
+        clauses.push(vec![neg(lits[i]), s[i][0]]);
+        clauses.push(vec![neg(s[i - 1][0]), s[i][0]]);
+        for j in 1..k {
+            clauses.push(vec![neg(lits[i]), neg(s[i - 1][j - 1]), s[i][j]]);
+            clauses.push(vec![neg(s[i - 1][j]), s[i][j]]);
+        }
+        clauses.push(vec![neg(lits[i]), neg(s[i - 1][k - 1])]);
+    }
+    clauses.push(vec![neg(lits[n - 1]), neg(s[n - 2][k - 1])]);
+    clauses
+}
+
+// Encode a DNF (a disjunction of conjunctive terms) as CNF without distributing.
+// Each term gets a fresh variable from next_var that implies every literal of the
+// term, and some term variable must be true. Projected onto the original variables
+// the models of the result are exactly the assignments satisfying the DNF.
+pub fn dnf_to_cnf(dnf: &[Vec<Literal>], next_var: &mut VarId) -> Vec<Vec<Literal>> {
+    let mut f = Vec::new();
+    let mut any = Vec::with_capacity(dnf.len());
+    for term in dnf {
+        let t = Literal(*next_var, true);
+        *next_var += 1;
+        for &lit in term {
+            f.push(vec![neg(t), lit]);
+        }
+        any.push(t);
+    }
+    f.push(any);
+    f
+}
+
+// Whether a implies b over the first num_vars variables, checked by refuting a ∧ ¬b.
+// The negation of b is a DNF with one term per clause, encoded with dnf_to_cnf.
+fn implies(a: &[Vec<Literal>], b: &[Vec<Literal>], num_vars: usize) -> bool {
+    let negated: Vec<Vec<Literal>> = b.iter().map(|c| c.iter().map(|&lit| neg(lit)).collect()).collect();
+    let mut next_var = num_vars as VarId;
+    let mut miter = a.to_vec();
+    miter.extend(dnf_to_cnf(&negated, &mut next_var));
+    solve(&miter, next_var as usize) == Solution::Unsat
+}
+
+// Whether two formulas over the first num_vars variables have exactly the same models
+pub fn are_equivalent(a: &[Vec<Literal>], b: &[Vec<Literal>], num_vars: usize) -> bool {
+    implies(a, b, num_vars) && implies(b, a, num_vars)
+}
+
+// Encode scheduling each interval, given as (earliest start, length), at a start slot
+// in 0..slots so that it ends by slots and no two intervals share a slot. Start
+// variables are allocated from next_var upwards, interval i starting at slot s is the
+// variable base + i·slots + s, and base is returned alongside the clauses.
+pub fn no_overlap(intervals: &[(u32, u32)], slots: u32, next_var: &mut VarId) -> (usize, Vec<Vec<Literal>>) {
+    let base = *next_var as usize;
+    let start = |i: usize, s: u32| Literal((base + i * slots as usize + s as usize) as VarId, true);
+    *next_var += (intervals.len() * slots as usize) as VarId;
+
+    let mut f = Vec::new();
+    for (i, &(earliest, len)) in intervals.iter().enumerate() {
+        let feasible = |s: u32| s >= earliest && s + len <= slots;
+        // Starts exactly once, somewhere it fits
+        f.push((0..slots).filter(|&s| feasible(s)).map(|s| start(i, s)).collect());
+        for s in (0..slots).filter(|&s| !feasible(s)) {
+            f.push(vec![neg(start(i, s))]);
+        }
+        for s in 0..slots {
+            for t in s + 1..slots {
+                f.push(vec![neg(start(i, s)), neg(start(i, t))]);
+            }
+        }
+    }
+
+    // Two starts conflict when the time they cover overlaps
+    for (i, &(_, a)) in intervals.iter().enumerate() {
+        for (j, &(_, b)) in intervals.iter().enumerate().skip(i + 1) {
+            for s in 0..slots {
+                for t in 0..slots {
+                    if s < t + b && t < s + a {
+                        f.push(vec![neg(start(i, s)), neg(start(j, t))]);
+                    }
+                }
+            }
+        }
+    }
+    (base, f)
+}
+
+// The start slot of each interval in a model of no_overlap's encoding with the given base
+pub fn decode_schedule(num_intervals: usize, slots: u32, base: usize, model: &[bool]) -> Vec<u32> {
+    (0..num_intervals)
+        .map(|i| (0..slots).find(|&s| model[base + i * slots as usize + s as usize]).expect("Every interval is scheduled in a model."))
+        .collect()
+}
+
+// A boolean expression over variables, for encoding with tseitin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Var(VarId),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Implies(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    // The value of the expression with each variable v set to model[v]
+    pub fn eval(&self, model: &[bool]) -> bool {
+        match self {
+            Expr::Var(v) => model[*v as usize],
+            Expr::Not(e) => !e.eval(model),
+            Expr::And(a, b) => a.eval(model) && b.eval(model),
+            Expr::Or(a, b) => a.eval(model) || b.eval(model),
+            Expr::Implies(a, b) => !a.eval(model) || b.eval(model),
+        }
+    }
+}
+
+// Add the clauses defining e to f, returning the literal equal to e
+fn tseitin_into(e: &Expr, next_var: &mut VarId, f: &mut Vec<Vec<Literal>>) -> Literal {
+    match e {
+        Expr::Var(v) => Literal(*v, true),
+        Expr::Not(e) => neg(tseitin_into(e, next_var, f)),
+        Expr::And(a, b) => {
+            let (a, b) = (tseitin_into(a, next_var, f), tseitin_into(b, next_var, f));
+            let t = Literal(*next_var, true);
+            *next_var += 1;
+            f.extend([vec![neg(t), a], vec![neg(t), b], vec![t, neg(a), neg(b)]]);
+            t
+        }
+        Expr::Or(a, b) => {
+            let (a, b) = (tseitin_into(a, next_var, f), tseitin_into(b, next_var, f));
+            let t = Literal(*next_var, true);
+            *next_var += 1;
+            f.extend([vec![neg(t), a, b], vec![t, neg(a)], vec![t, neg(b)]]);
+            t
+        }
+        // a → b is ¬a ∨ b
+        Expr::Implies(a, b) => {
+            let (a, b) = (neg(tseitin_into(a, next_var, f)), tseitin_into(b, next_var, f));
+            let t = Literal(*next_var, true);
+            *next_var += 1;
+            f.extend([vec![neg(t), a, b], vec![t, neg(a)], vec![t, neg(b)]]);
+            t
+        }
+    }
+}
+
+// Tseitin encode an expression, giving each And, Or and Implies a fresh variable from next_var
+// defined to be equal to it, while variables and negations map onto literals directly. Returns
+// the literal equal to the whole expression along with the clauses, the expression holds
+// exactly when that literal is asserted true, and the clauses alone are always satisfiable.
+pub fn tseitin(e: &Expr, next_var: &mut VarId) -> (Literal, Vec<Vec<Literal>>) {
+    let mut f = Vec::new();
+    let top = tseitin_into(e, next_var, &mut f);
+    (top, f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certify::{certify_sat, CertResult};
+
+    // Every row of num_inputs variables that satisfies f
+    fn models(f: &[Vec<Literal>], num_inputs: usize) -> Vec<u32> {
+        (0..1u32 << num_inputs)
+            .filter(|row| {
+                let model: Vec<bool> = (0..num_inputs).map(|i| row >> i & 1 == 1).collect();
+                certify_sat(f, &model) == CertResult::Valid
+            })
+            .collect()
+    }
+
+    #[test]
+    fn check_truth_table_xor() {
+        let f = from_truth_table(2, &[0b01, 0b10]);
+        assert_eq!(f, vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, false), Literal(1, false)],
+        ]);
+        assert_eq!(models(&f, 2), vec![0b01, 0b10]);
+    }
+
+    #[test]
+    fn check_truth_table_extremes() {
+        assert_eq!(from_truth_table(2, &[0, 1, 2, 3]), Vec::<Vec<Literal>>::new());
+        assert_eq!(models(&from_truth_table(3, &[]), 3), vec![]);
+        assert_eq!(models(&from_truth_table(3, &[5]), 3), vec![5]);
+    }
+
+    #[test]
+    fn check_dnf_to_cnf() {
+        // (0 ∧ ¬1) ∨ (1 ∧ 2) ∨ (¬0 ∧ ¬1 ∧ ¬2)
+        let dnf = vec![
+            vec![Literal(0, true), Literal(1, false)],
+            vec![Literal(1, true), Literal(2, true)],
+            vec![Literal(0, false), Literal(1, false), Literal(2, false)],
+        ];
+        let mut next_var = 3;
+        let f = dnf_to_cnf(&dnf, &mut next_var);
+        assert_eq!(next_var, 6);
+
+        for row in 0..1u32 << 3 {
+            let model: Vec<bool> = (0..3).map(|i| row >> i & 1 == 1).collect();
+            let expected = dnf.iter().any(|term| term.iter().all(|lit| model[lit.0 as usize] == lit.1));
+
+            let mut g = f.clone();
+            g.extend((0..3).map(|v| vec![Literal(v, model[v as usize])]));
+            assert_eq!(matches!(solve(&g, 6), Solution::Sat(_)), expected, "row {row:03b}");
+        }
+
+        assert_eq!(dnf_to_cnf(&[], &mut next_var), vec![vec![]]);
+    }
+
+    #[test]
+    fn check_tseitin() {
+        use Expr::*;
+        let var = |v| Box::new(Var(v));
+
+        // (a ∧ b) ∨ ¬c
+        let e = Or(Box::new(And(var(0), var(1))), Box::new(Not(var(2))));
+        let mut next_var = 3;
+        let (top, mut f) = tseitin(&e, &mut next_var);
+        assert_eq!(next_var, 5);
+        f.push(vec![top]);
+        let Solution::Sat(model) = solve(&f, 5) else {
+            panic!("Expected a model.");
+        };
+        assert!(e.eval(&model));
+
+        // Fixing the inputs, the encoding is satisfiable exactly when the expression holds
+        let e = Implies(Box::new(Or(var(0), Box::new(Not(var(1))))), Box::new(And(var(2), var(0))));
+        let mut next_var = 3;
+        let (top, f) = tseitin(&e, &mut next_var);
+        for row in 0..1u32 << 3 {
+            let model: Vec<bool> = (0..3).map(|i| row >> i & 1 == 1).collect();
+            let mut g = f.clone();
+            g.push(vec![top]);
+            g.extend((0..3).map(|v| vec![Literal(v, model[v as usize])]));
+            assert_eq!(matches!(solve(&g, next_var as usize), Solution::Sat(_)), e.eval(&model), "row {row:03b}");
+        }
+
+        let (top, f) = tseitin(&Not(var(4)), &mut next_var);
+        assert_eq!((top, f), (Literal(4, false), vec![]));
+    }
+
+    #[test]
+    fn check_sequential_at_most_k() {
+        let lits: Vec<Literal> = (0..5).map(|v| Literal(v, true)).collect();
+        let mut next_var = 5;
+        let f = sequential_at_most_k(&lits, 2, &mut next_var);
+        assert_eq!(next_var, 5 + 4 * 2);
+
+        // Satisfiable exactly when at most two of the five are fixed true
+        for row in 0..1u32 << 5 {
+            let mut g = f.clone();
+            g.extend((0..5).map(|v| vec![Literal(v, row >> v & 1 == 1)]));
+            let sat = matches!(solve(&g, next_var as usize), Solution::Sat(_));
+            assert_eq!(sat, row.count_ones() <= 2, "row {row:05b}");
+        }
+
+        let mut next_var = 5;
+        assert_eq!(sequential_at_most_k(&lits, 5, &mut next_var), Vec::<Vec<Literal>>::new());
+        assert_eq!(sequential_at_most_k(&lits[..2], 0, &mut next_var), vec![
+            vec![Literal(0, false)],
+            vec![Literal(1, false)],
+        ]);
+        assert_eq!(next_var, 5);
+    }
+
+    #[test]
+    fn check_are_equivalent() {
+        // Exactly one of 0 and 1, as a truth table and written out by hand with the clauses swapped
+        let a = from_truth_table(2, &[0b01, 0b10]);
+        let b = vec![
+            vec![Literal(1, false), Literal(0, false)],
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, true), Literal(1, true), Literal(2, true)],
+        ];
+        assert!(are_equivalent(&a, &b, 3));
+
+        // At most one of them admits both false as well
+        let c = vec![vec![Literal(0, false), Literal(1, false)]];
+        assert!(!are_equivalent(&a, &c, 2));
+        assert!(!are_equivalent(&c, &a, 2));
+
+        let contradiction = vec![vec![Literal(0, true)], vec![Literal(0, false)]];
+        assert!(are_equivalent(&contradiction, &[vec![]], 1));
+        assert!(!are_equivalent(&contradiction, &[], 1));
+    }
+
+    #[test]
+    fn check_no_overlap() {
+        // Two intervals of length 2 that may both start straight away
+        let intervals = [(0, 2), (0, 2)];
+        let mut next_var = 1;
+        let (base, f) = no_overlap(&intervals, 3, &mut next_var);
+        assert_eq!((base, next_var), (1, 7));
+        assert_eq!(solve(&f, next_var as usize), Solution::Unsat);
+
+        let mut next_var = 1;
+        let (base, f) = no_overlap(&intervals, 4, &mut next_var);
+        let Solution::Sat(model) = solve(&f, next_var as usize) else {
+            panic!("Four slots fit both intervals.");
+        };
+        let starts = decode_schedule(2, 4, base, &model);
+        assert!(starts == [0, 2] || starts == [2, 0], "{starts:?}");
+
+        // A later release forces the order
+        let intervals = [(0, 2), (1, 1), (0, 1)];
+        let mut next_var = 0;
+        let (base, f) = no_overlap(&intervals, 4, &mut next_var);
+        let Solution::Sat(model) = solve(&f, next_var as usize) else {
+            panic!("Four slots fit all three intervals.");
+        };
+        let starts = decode_schedule(3, 4, base, &model);
+        for (i, (&(earliest, a), &s)) in intervals.iter().zip(&starts).enumerate() {
+            assert!(s >= earliest && s + a <= 4);
+            for (&(_, b), &t) in intervals.iter().zip(&starts).skip(i + 1) {
+                assert!(s + a <= t || t + b <= s, "{starts:?}");
+            }
+        }
+    }
+}
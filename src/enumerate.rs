@@ -0,0 +1,276 @@
+use crate::{dpll::{dpll_with_config, full_unit_prop, Config, Literal, Stats, VarId}, heuristics::{DecisionHeuristic, FirstUnassigned}};
+
+// The clause ruling out exactly the given full assignment
+pub fn blocking_clause(model: &[bool]) -> Vec<Literal> {
+    model.iter().enumerate().map(|(v, &b)| Literal(v as VarId, !b)).collect()
+}
+
+// Number of variables on which two models disagree
+pub fn hamming(a: &[bool], b: &[bool]) -> usize {
+    a.iter().zip(b).filter(|(x, y)| x != y).count()
+}
+
+// Search for a model of f with heuristic, leaving it in var_assigns, and return whether there is one
+fn find_model(var_assigns: &mut [Option<bool>], f: &[Vec<Literal>], heuristic: &mut dyn DecisionHeuristic) -> bool {
+    // Pure literal elimination would rule out models, and without a depth limit the search always
+    // reaches a verdict, so no enumeration is cut short by a search that gave up
+    let config = Config { pure_literal: false, max_depth: usize::MAX, ..Config::default() };
+    dpll_with_config(var_assigns, &mut f.to_vec(), heuristic, &mut Stats::default(), config)
+        .expect("A search without a depth limit or a stop condition decides the formula.")
+}
+
+// Branch on the first unassigned variable, trying the polarity
+// that the fewest of the models found so far have
+struct AwayFrom {
+    trues: Vec<usize>,
+    found: usize,
+}
+
+impl DecisionHeuristic for AwayFrom {
+    fn decide(&mut self, var_assigns: &[Option<bool>], _f: &[Vec<Literal>]) -> Option<Literal> {
+        let x = var_assigns.iter().position(|&x| x.is_none())?;
+        Some(Literal(x as VarId, 2 * self.trues[x] < self.found || self.found == 0))
+    }
+}
+
+// Find up to k models that are spread out over the solution space.
+// A pool of candidates is enumerated while steering each solve away from the
+// earlier models, then the k candidates are picked greedily so that each one
+// maximises its minimum Hamming distance to those already chosen.
+pub fn diverse_models(f: &[Vec<Literal>], num_vars: usize, k: usize) -> Vec<Vec<bool>> {
+    let mut g = f.to_vec();
+    let mut away = AwayFrom { trues: vec![0; num_vars], found: 0 };
+    let mut pool: Vec<Vec<bool>> = Vec::new();
+
+    while pool.len() < 4 * k {
+        let mut var_assigns = vec![None; num_vars];
+        if !find_model(&mut var_assigns, &g, &mut away) {
+            break;
+        }
+        let model: Vec<bool> = var_assigns.iter().map(|v| v.unwrap_or(true)).collect();
+
+        away.found += 1;
+        for (t, &b) in away.trues.iter_mut().zip(&model) {
+            *t += b as usize;
+        }
+        g.push(blocking_clause(&model));
+        pool.push(model);
+    }
+
+    let mut chosen: Vec<Vec<bool>> = Vec::new();
+    while chosen.len() < k && !pool.is_empty() {
+        let best = (0..pool.len())
+            .max_by_key(|&i| chosen.iter().map(|m| hamming(m, &pool[i])).min().unwrap_or(0))
+            .unwrap();
+        chosen.push(pool.swap_remove(best));
+    }
+    chosen
+}
+
+// Find up to k distinct models, stopping as soon as k have been found
+pub fn first_k_models(f: &[Vec<Literal>], num_vars: usize, k: usize) -> Vec<Vec<bool>> {
+    let mut g = f.to_vec();
+    let mut models = Vec::new();
+
+    while models.len() < k {
+        let mut var_assigns = vec![None; num_vars];
+        if !find_model(&mut var_assigns, &g, &mut FirstUnassigned) {
+            break;
+        }
+        let model: Vec<bool> = var_assigns.iter().map(|v| v.unwrap_or(true)).collect();
+        g.push(blocking_clause(&model));
+        models.push(model);
+    }
+    models
+}
+
+// Every model of f over num_vars variables. Each solve finds a partial assignment under which
+// all clauses are satisfied, its unassigned variables are expanded into every combination and
+// the assignment itself is blocked before solving again.
+pub fn all_models(f: &[Vec<Literal>], num_vars: usize) -> Vec<Vec<bool>> {
+    let mut g = f.to_vec();
+    let mut models = Vec::new();
+
+    loop {
+        let mut var_assigns = vec![None; num_vars];
+        if !find_model(&mut var_assigns, &g, &mut FirstUnassigned) {
+            return models;
+        }
+
+        let free: Vec<usize> = (0..num_vars).filter(|&v| var_assigns[v].is_none()).collect();
+        for bits in 0..1u64 << free.len() {
+            let mut model: Vec<bool> = var_assigns.iter().map(|v| v.unwrap_or(false)).collect();
+            for (i, &v) in free.iter().enumerate() {
+                model[v] = bits >> i & 1 == 1;
+            }
+            models.push(model);
+        }
+
+        let block: Vec<Literal> = var_assigns.iter().enumerate()
+            .filter_map(|(v, b)| b.map(|b| Literal(v as VarId, !b)))
+            .collect();
+        if block.is_empty() {
+            return models;
+        }
+        g.push(block);
+    }
+}
+
+// Compute base^exp mod m by repeated squaring
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut acc = 1 % m;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            acc = (acc as u128 * base as u128 % m as u128) as u64;
+        }
+        base = (base as u128 * base as u128 % m as u128) as u64;
+        exp >>= 1;
+    }
+    acc
+}
+
+// Count the models of f modulo m by splitting on variables, everything left
+// unassigned once all clauses are satisfied doubles the count
+fn count_mod(var_assigns: &mut [Option<bool>], f: &mut Vec<Vec<Literal>>, m: u64) -> u64 {
+    // Only unit propagation is safe here, pure literal elimination would lose models
+    full_unit_prop(var_assigns, f);
+
+    if f.iter().any(|c| c.is_empty()) {
+        return 0;
+    }
+    if f.is_empty() {
+        let free = var_assigns.iter().filter(|v| v.is_none()).count();
+        return pow_mod(2, free as u64, m);
+    }
+
+    let x = f[0][0].0;
+    let mut total = 0;
+    for b in [true, false] {
+        let mut g = f.clone();
+        let mut assigns = var_assigns.to_vec();
+        g.push(vec![Literal(x, b)]);
+        total = (total + count_mod(&mut assigns, &mut g, m)) % m;
+    }
+    total
+}
+
+// Count the models of a formula over num_vars variables modulo prime,
+// the result is exact modulo prime however large the true count is
+pub fn count_models_mod(f: &[Vec<Literal>], num_vars: usize, prime: u64) -> u64 {
+    let mut var_assigns = vec![None; num_vars];
+    count_mod(&mut var_assigns, &mut f.to_vec(), prime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certify::{certify_sat, CertResult};
+
+    #[test]
+    fn check_blocking_clause() {
+        assert_eq!(blocking_clause(&[true, false]), vec![Literal(0, false), Literal(1, true)]);
+    }
+
+    #[test]
+    fn check_diverse_models() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(2, false), Literal(3, true)],
+            vec![Literal(4, true), Literal(5, false), Literal(6, true)],
+        ];
+        let models = diverse_models(&c, 8, 3);
+
+        assert_eq!(models.len(), 3);
+        for (i, a) in models.iter().enumerate() {
+            assert_eq!(certify_sat(&c, a), CertResult::Valid);
+            for b in &models[i + 1..] {
+                assert!(hamming(a, b) >= 3, "{a:?} and {b:?} are too close");
+            }
+        }
+    }
+
+    #[test]
+    fn check_diverse_models_few() {
+        let c = vec![vec![Literal(0, true)], vec![Literal(0, false), Literal(1, false)]];
+        assert_eq!(diverse_models(&c, 2, 5), vec![vec![true, false]]);
+    }
+
+    #[test]
+    fn check_first_k_models() {
+        // Four models with 0 true and one with it false
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, true), Literal(2, true)],
+        ];
+        let models = first_k_models(&c, 3, 2);
+        assert_eq!(models.len(), 2);
+        assert_ne!(models[0], models[1]);
+        assert!(models.iter().all(|m| certify_sat(&c, m) == CertResult::Valid));
+
+        let mut all = first_k_models(&c, 3, 10);
+        assert_eq!(all.len(), 5);
+        all.sort();
+        all.dedup();
+        assert_eq!(all.len(), 5);
+        assert_eq!(first_k_models(&c, 3, 0), Vec::<Vec<bool>>::new());
+    }
+
+    #[test]
+    fn check_all_models() {
+        // Exactly one of 0, 1 and 2
+        let mut c = vec![vec![Literal(0, true), Literal(1, true), Literal(2, true)]];
+        for (a, b) in [(0, 1), (0, 2), (1, 2)] {
+            c.push(vec![Literal(a, false), Literal(b, false)]);
+        }
+        let mut models = all_models(&c, 3);
+        models.sort();
+        assert_eq!(models, vec![vec![false, false, true], vec![false, true, false], vec![true, false, false]]);
+
+        // Variables the formula leaves free are expanded into every combination
+        let mut models = all_models(&[vec![Literal(1, true)]], 3);
+        assert_eq!(models.len(), 4);
+        models.sort();
+        models.dedup();
+        assert_eq!(models.len(), 4);
+        assert!(models.iter().all(|m| m[1]));
+
+        assert_eq!(all_models(&[], 2).len(), 4);
+        assert!(all_models(&[vec![]], 2).is_empty());
+        assert_eq!(all_models(&c, 3).len() as u64, brute_force_count(&c, 3));
+    }
+
+    fn brute_force_count(f: &[Vec<Literal>], num_vars: usize) -> u64 {
+        (0..1u32 << num_vars)
+            .filter(|bits| {
+                let model: Vec<bool> = (0..num_vars).map(|v| bits >> v & 1 == 1).collect();
+                certify_sat(f, &model) == CertResult::Valid
+            })
+            .count() as u64
+    }
+
+    #[test]
+    fn check_count_models_mod() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(1, false), Literal(2, true), Literal(3, false)],
+            vec![Literal(0, false), Literal(3, true)],
+            vec![Literal(4, true), Literal(2, false)],
+        ];
+        let exact = brute_force_count(&c, 6);
+        for p in [2, 3, 5, 7, 1_000_000_007] {
+            assert_eq!(count_models_mod(&c, 6, p), exact % p);
+        }
+
+        let d = vec![vec![Literal(0, true)], vec![Literal(0, false)]];
+        assert_eq!(count_models_mod(&d, 3, 7), 0);
+    }
+
+    #[test]
+    fn check_count_models_mod_large() {
+        // 2^100 and 3 * 2^98 modulo 1_000_000_007
+        let c = vec![vec![Literal(0, true), Literal(1, true)]];
+        assert_eq!(count_models_mod(&[], 100, 1_000_000_007), 976_371_285);
+        assert_eq!(count_models_mod(&c, 100, 1_000_000_007), 482_278_462);
+    }
+}
@@ -0,0 +1,47 @@
+use crate::dpll::{Literal, VarId};
+
+// Build the variable interaction graph of a formula as an adjacency list,
+// two variables are adjacent if they occur together in some clause. Neighbours are
+// VarIds rather than u16s, so formulas with more than 65536 variables fit as well.
+pub fn interaction_graph(f: &[Vec<Literal>], num_vars: usize) -> Vec<Vec<VarId>> {
+    let mut adj = vec![Vec::new(); num_vars];
+    for clause in f {
+        for a in clause {
+            for b in clause {
+                if a.0 != b.0 {
+                    adj[a.0 as usize].push(b.0);
+                }
+            }
+        }
+    }
+
+    // Each pair is seen once per shared clause, so tidy up the duplicates
+    for vs in adj.iter_mut() {
+        vs.sort_unstable();
+        vs.dedup();
+    }
+    adj
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_interaction_graph() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, false)],
+            vec![Literal(1, true), Literal(2, true), Literal(0, false)],
+            vec![Literal(3, false), Literal(3, false)],
+            vec![Literal(2, false), Literal(1, true)],
+        ];
+        let g = interaction_graph(&c, 5);
+        assert_eq!(g, vec![
+            vec![1, 2],
+            vec![0, 2],
+            vec![0, 1],
+            vec![],
+            vec![],
+        ]);
+    }
+}
@@ -0,0 +1,340 @@
+use crate::dpll::{Literal, VarId};
+
+// A policy for choosing the next decision literal during search,
+// the polarity of the returned literal is tried first
+pub trait DecisionHeuristic {
+    fn decide(&mut self, var_assigns: &[Option<bool>], f: &[Vec<Literal>]) -> Option<Literal>;
+
+    // Called with the most recent decision whenever propagation below it runs into an empty clause
+    fn on_conflict(&mut self, _decision: Literal) {}
+}
+
+// So a borrowed heuristic can be handed to a solver that owns its heuristic
+impl<H: DecisionHeuristic + ?Sized> DecisionHeuristic for &mut H {
+    fn decide(&mut self, var_assigns: &[Option<bool>], f: &[Vec<Literal>]) -> Option<Literal> {
+        (**self).decide(var_assigns, f)
+    }
+
+    fn on_conflict(&mut self, decision: Literal) {
+        (**self).on_conflict(decision)
+    }
+}
+
+// Branch on the lowest numbered unassigned variable, trying true first
+pub struct FirstUnassigned;
+
+impl DecisionHeuristic for FirstUnassigned {
+    fn decide(&mut self, var_assigns: &[Option<bool>], _f: &[Vec<Literal>]) -> Option<Literal> {
+        let x = var_assigns.iter().position(|&x| x.is_none())?;
+        Some(Literal(x as VarId, true))
+    }
+}
+
+// Keep branching within one community of variables until it is fully assigned,
+// then move on to the community of the lowest numbered unassigned variable
+pub struct CommunityBranching {
+    communities: Vec<usize>,
+    current: Option<usize>,
+}
+
+impl CommunityBranching {
+    // communities[v] is the community that variable v belongs to
+    pub fn new(communities: Vec<usize>) -> Self {
+        CommunityBranching { communities, current: None }
+    }
+}
+
+impl DecisionHeuristic for CommunityBranching {
+    fn decide(&mut self, var_assigns: &[Option<bool>], _f: &[Vec<Literal>]) -> Option<Literal> {
+        // Stay in the current community if anything is left in it
+        if let Some(c) = self.current {
+            let x = (0..var_assigns.len())
+                .find(|&v| var_assigns[v].is_none() && self.communities[v] == c);
+            if let Some(x) = x {
+                return Some(Literal(x as VarId, true));
+            }
+        }
+
+        let x = var_assigns.iter().position(|&x| x.is_none())?;
+        self.current = Some(self.communities[x]);
+        Some(Literal(x as VarId, true))
+    }
+}
+
+// Once any activity passes this, every activity and the bump increment are divided by it
+pub const ACTIVITY_LIMIT: f64 = 1e100;
+
+// VSIDS style variable activities. Rather than decaying every score, the amount added
+// by a bump grows after each decay, which keeps the same relative ordering far cheaper.
+pub struct Activity {
+    scores: Vec<f64>,
+    inc: f64,
+    decay: f64,
+    // How many times the scores have been rescaled to stay finite
+    pub rescales: usize,
+}
+
+impl Activity {
+    // decay is the factor, below 1, that older bumps are worth relative to newer ones
+    pub fn new(num_vars: usize, decay: f64) -> Self {
+        Activity { scores: vec![0.0; num_vars], inc: 1.0, decay, rescales: 0 }
+    }
+
+    // Start each variable v at scores[v] rather than 0
+    pub fn from_scores(scores: Vec<f64>, decay: f64) -> Self {
+        Activity { scores, inc: 1.0, decay, rescales: 0 }
+    }
+
+    pub fn score(&self, v: VarId) -> f64 {
+        self.scores[v as usize]
+    }
+
+    pub fn bump(&mut self, v: VarId) {
+        self.bump_by(v, 1.0);
+    }
+
+    // Bump v by weight times the usual amount
+    pub fn bump_by(&mut self, v: VarId, weight: f64) {
+        self.scores[v as usize] += weight * self.inc;
+        if self.scores[v as usize] > ACTIVITY_LIMIT {
+            for s in &mut self.scores {
+                *s /= ACTIVITY_LIMIT;
+            }
+            self.inc /= ACTIVITY_LIMIT;
+            self.rescales += 1;
+        }
+    }
+
+    pub fn decay(&mut self) {
+        self.inc /= self.decay;
+    }
+}
+
+// Branch on the unassigned variable with the highest activity, trying true first. A variable
+// is bumped each time a conflict is reached directly below its decision, so the search comes
+// back to the variables that keep failing rather than retrying ones that do not matter.
+pub struct Vsids {
+    activity: Activity,
+}
+
+impl Vsids {
+    pub fn new(num_vars: usize) -> Self {
+        Vsids { activity: Activity::new(num_vars, 0.95) }
+    }
+
+    // The unassigned variable with the highest activity, the lowest numbered one on ties
+    pub fn branch(&self, var_assigns: &[Option<bool>]) -> Option<VarId> {
+        let mut best: Option<VarId> = None;
+        for v in (0..var_assigns.len() as VarId).filter(|&v| var_assigns[v as usize].is_none()) {
+            if best.is_none_or(|b| self.activity.score(v) > self.activity.score(b)) {
+                best = Some(v);
+            }
+        }
+        best
+    }
+}
+
+impl DecisionHeuristic for Vsids {
+    fn decide(&mut self, var_assigns: &[Option<bool>], _f: &[Vec<Literal>]) -> Option<Literal> {
+        Some(Literal(self.branch(var_assigns)?, true))
+    }
+
+    fn on_conflict(&mut self, decision: Literal) {
+        self.activity.bump(decision.0);
+        self.activity.decay();
+    }
+}
+
+// Static Jeroslow-Wang branching. Each literal scores 2^-|c| for every clause c it occurs in, so
+// literals of short clauses count for far more, and the scores are fixed when it is created.
+// Branches on the unassigned variable with the highest scoring literal, trying that literal first.
+pub struct JeroslowWang {
+    // Indexed by Literal::index
+    scores: Vec<f64>,
+}
+
+impl JeroslowWang {
+    pub fn new(f: &[Vec<Literal>], num_vars: usize) -> Self {
+        let mut scores = vec![0.0; 2 * num_vars];
+        for c in f {
+            let weight = 0.5f64.powi(c.len() as i32);
+            for lit in c {
+                scores[lit.index()] += weight;
+            }
+        }
+        JeroslowWang { scores }
+    }
+
+    pub fn score(&self, lit: Literal) -> f64 {
+        self.scores[lit.index()]
+    }
+}
+
+impl DecisionHeuristic for JeroslowWang {
+    // The lowest numbered variable on ties, and true before false
+    fn decide(&mut self, var_assigns: &[Option<bool>], _f: &[Vec<Literal>]) -> Option<Literal> {
+        let mut best: Option<Literal> = None;
+        for v in (0..var_assigns.len() as VarId).filter(|&v| var_assigns[v as usize].is_none()) {
+            for lit in [Literal(v, true), Literal(v, false)] {
+                if best.is_none_or(|b| self.score(lit) > self.score(b)) {
+                    best = Some(lit);
+                }
+            }
+        }
+        best
+    }
+}
+
+// The decision heuristics a Solver can be created with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    FirstUnassigned,
+    JeroslowWang,
+    #[default]
+    Vsids,
+}
+
+impl Strategy {
+    // A fresh heuristic of this kind, Jeroslow-Wang scoring the clauses of f
+    pub fn heuristic(self, f: &[Vec<Literal>], num_vars: usize) -> Box<dyn DecisionHeuristic> {
+        match self {
+            Strategy::FirstUnassigned => Box::new(FirstUnassigned),
+            Strategy::JeroslowWang => Box::new(JeroslowWang::new(f, num_vars)),
+            Strategy::Vsids => Box::new(Vsids::new(num_vars)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dpll::{dpll_with_heuristic, dpll_with_stats, Stats};
+
+    #[test]
+    fn check_first_unassigned() {
+        let vs = [Some(true), None, None];
+        assert_eq!(FirstUnassigned.decide(&vs, &[]), Some(Literal(1, true)));
+        assert_eq!(FirstUnassigned.decide(&[Some(false)], &[]), None);
+    }
+
+    #[test]
+    fn check_community_branching() {
+        let mut h = CommunityBranching::new(vec![0, 1, 0, 1]);
+        let mut vs = [None; 4];
+
+        let x = h.decide(&vs, &[]).unwrap();
+        assert_eq!(x, Literal(0, true));
+        vs[0] = Some(true);
+
+        // Variable 1 is unassigned but 2 shares the community of the last decision
+        let x = h.decide(&vs, &[]).unwrap();
+        assert_eq!(x, Literal(2, true));
+        vs[2] = Some(true);
+
+        let x = h.decide(&vs, &[]).unwrap();
+        assert_eq!(x, Literal(1, true));
+        vs[1] = Some(true);
+
+        let x = h.decide(&vs, &[]).unwrap();
+        assert_eq!(x, Literal(3, true));
+    }
+
+    #[test]
+    fn check_dpll_community() {
+        let mut c = vec![
+            vec![Literal(0, true), Literal(2, true)],
+            vec![Literal(0, false), Literal(2, false)],
+            vec![Literal(1, true), Literal(3, true)],
+            vec![Literal(1, false), Literal(3, false)],
+        ];
+        let mut vs = [None; 4];
+        let mut h = CommunityBranching::new(vec![0, 1, 0, 1]);
+
+        assert!(dpll_with_heuristic(&mut vs, &mut c, &mut h, &mut Stats::default()));
+        assert_ne!(vs[0], vs[2]);
+        assert_ne!(vs[1], vs[3]);
+    }
+
+    #[test]
+    fn check_activity_rescale() {
+        let mut a = Activity::new(3, 0.5);
+        // Doubling the increment each round passes 1e100 after a few hundred rounds
+        for _ in 0..1000 {
+            a.bump(0);
+            a.bump(1);
+            a.bump(0);
+            a.decay();
+        }
+
+        assert!(a.rescales > 0);
+        assert!((0..3).all(|v| a.score(v).is_finite() && a.score(v) <= ACTIVITY_LIMIT));
+        // 0 got exactly twice the bumps of 1 every round, and 2 was never bumped
+        assert!((a.score(0) / a.score(1) - 2.0).abs() < 1e-9);
+        assert_eq!(a.score(2), 0.0);
+    }
+
+    #[test]
+    fn check_vsids_branch() {
+        let mut h = Vsids::new(3);
+        assert_eq!(h.branch(&[None; 3]), Some(0));
+        h.on_conflict(Literal(2, false));
+        assert_eq!(h.branch(&[None; 3]), Some(2));
+        assert_eq!(h.branch(&[None, None, Some(true)]), Some(0));
+        assert_eq!(h.decide(&[Some(true); 3], &[]), None);
+    }
+
+    #[test]
+    fn check_jeroslow_wang() {
+        // ¬4 is in two binary clauses, everything else only in ternary ones
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true), Literal(2, true)],
+            vec![Literal(0, true), Literal(1, true), Literal(3, true)],
+            vec![Literal(4, false), Literal(2, true)],
+            vec![Literal(4, false), Literal(3, true)],
+        ];
+        let mut h = JeroslowWang::new(&c, 5);
+        assert_eq!(h.score(Literal(4, false)), 0.5);
+        assert_eq!(h.score(Literal(2, true)), 0.375);
+        assert_eq!(h.decide(&[None; 5], &c), Some(Literal(4, false)));
+        assert_eq!(FirstUnassigned.decide(&[None; 5], &c), Some(Literal(0, true)));
+
+        // 2 and 3 tie once 4 is assigned, and 0 ties with them after that
+        assert_eq!(h.decide(&[None, None, None, None, Some(false)], &c), Some(Literal(2, true)));
+        assert_eq!(h.decide(&[None, None, Some(true), Some(true), Some(false)], &c), Some(Literal(0, true)));
+        assert_eq!(h.decide(&[Some(true); 5], &c), None);
+
+        let mut stats = Stats::default();
+        let mut vs = [None; 5];
+        assert!(dpll_with_heuristic(&mut vs, &mut c.clone(), &mut h, &mut stats));
+        assert_eq!(crate::certify::certify_sat(&c, &vs.map(|v| v.unwrap_or(true))), crate::certify::CertResult::Valid);
+    }
+
+    #[test]
+    fn check_vsids_decisions() {
+        // Twelve independent pairs that are easy to satisfy, followed by every clause over the
+        // three variables after them. The naive order refutes those three again under every
+        // combination of the pairs, VSIDS goes straight back to them after each backtrack.
+        let mut c = Vec::new();
+        for i in 0..12u32 {
+            c.push(vec![Literal(2 * i, true), Literal(2 * i + 1, true)]);
+            c.push(vec![Literal(2 * i, false), Literal(2 * i + 1, false)]);
+        }
+        for bits in 0..8u32 {
+            c.push((0..3).map(|v| Literal(24 + v, bits >> v & 1 == 1)).collect());
+        }
+
+        let mut naive = Stats::default();
+        assert!(!dpll_with_stats(&mut [None; 27], &mut c.clone(), &mut naive));
+        let mut vsids = Stats::default();
+        assert!(!dpll_with_heuristic(&mut [None; 27], &mut c.clone(), &mut Vsids::new(27), &mut vsids));
+        assert!(vsids.decisions * 10 < naive.decisions, "{} vs {}", vsids.decisions, naive.decisions);
+
+        let mut c = Vec::new();
+        crate::cnf_parsing::parse_cnf_file_into("cnfs/uf20-01.cnf", &mut c).unwrap();
+        let model = match crate::dpll::solve_with_heuristic(&c, 20, &mut Vsids::new(20)) {
+            crate::dpll::Solution::Sat(model) => model,
+            s => panic!("Expected a model, got {s:?}."),
+        };
+        assert_eq!(crate::certify::certify_sat(&c, &model), crate::certify::CertResult::Valid);
+    }
+}
@@ -1,2 +1,19 @@
-mod dpll;
-mod cnf_parsing;
+pub mod dpll;
+pub mod cnf_parsing;
+pub mod graph;
+pub mod heuristics;
+pub mod certify;
+pub mod preprocess;
+pub mod enumerate;
+pub mod proof;
+pub mod model;
+pub mod local_search;
+pub mod solver;
+pub mod encodings;
+pub mod optimize;
+pub mod aig;
+pub mod cube;
+pub mod bench;
+pub mod dimacs;
+pub mod cdcl;
+pub mod binary;
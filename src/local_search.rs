@@ -0,0 +1,332 @@
+use crate::dpll::{solve, Literal, Solution, VarId};
+
+// A small xorshift64* generator so the search is reproducible from a seed
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        // The all zero state is a fixed point of xorshift
+        Rng(if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed })
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // Uniform in 0..n
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // Uniform in [0, 1)
+    pub(crate) fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// The local search state: a full assignment and, per clause, how many of its literals are true.
+// The clauses are normalised so each variable occurs at most once in a clause.
+struct State {
+    f: Vec<Vec<Literal>>,
+    assigns: Vec<bool>,
+    // occurs[v] lists the clauses variable v appears in
+    occurs: Vec<Vec<usize>>,
+    num_true: Vec<usize>,
+    // The currently unsatisfied clauses, with each clause's position in that list
+    unsat: Vec<usize>,
+    unsat_pos: Vec<usize>,
+    // Per variable, how many clauses flipping it would break (it is their only true literal)
+    // and make (it occurs in them and they are unsatisfied), kept up to date on every flip
+    break_count: Vec<usize>,
+    make_count: Vec<usize>,
+    // The step at which each variable was last flipped, 0 if never
+    flipped_at: Vec<usize>,
+}
+
+impl State {
+    fn new(f: &[Vec<Literal>], assigns: Vec<bool>) -> Self {
+        // Merge repeated literals and drop tautologies, which are always satisfied
+        let f: Vec<Vec<Literal>> = f.iter()
+            .filter_map(|clause| {
+                let mut c = clause.clone();
+                c.sort_unstable_by_key(|lit| (lit.0, lit.1));
+                c.dedup();
+                (!c.windows(2).any(|w| w[0].0 == w[1].0)).then_some(c)
+            })
+            .collect();
+
+        let num_vars = assigns.len();
+        let mut occurs = vec![Vec::new(); num_vars];
+        for (ci, clause) in f.iter().enumerate() {
+            for lit in clause {
+                occurs[lit.0 as usize].push(ci);
+            }
+        }
+        let mut s = State {
+            num_true: vec![0; f.len()],
+            unsat: Vec::new(),
+            unsat_pos: vec![usize::MAX; f.len()],
+            break_count: vec![0; num_vars],
+            make_count: vec![0; num_vars],
+            flipped_at: vec![0; num_vars],
+            f,
+            assigns,
+            occurs,
+        };
+        for ci in 0..s.f.len() {
+            let trues: Vec<VarId> = s.f[ci].iter().filter(|lit| s.assigns[lit.0 as usize] == lit.1).map(|lit| lit.0).collect();
+            s.num_true[ci] = trues.len();
+            match trues[..] {
+                [] => {
+                    s.mark_unsat(ci);
+                    for lit in &s.f[ci] {
+                        s.make_count[lit.0 as usize] += 1;
+                    }
+                }
+                [w] => s.break_count[w as usize] += 1,
+                _ => (),
+            }
+        }
+        s
+    }
+
+    fn mark_unsat(&mut self, ci: usize) {
+        self.unsat_pos[ci] = self.unsat.len();
+        self.unsat.push(ci);
+    }
+
+    fn mark_sat(&mut self, ci: usize) {
+        let pos = self.unsat_pos[ci];
+        let last = *self.unsat.last().unwrap();
+        self.unsat.swap_remove(pos);
+        if last != ci {
+            self.unsat_pos[last] = pos;
+        }
+        self.unsat_pos[ci] = usize::MAX;
+    }
+
+    // The variable of the true literal in clause ci other than v, if any
+    fn other_true(&self, ci: usize, v: VarId) -> Option<VarId> {
+        self.f[ci].iter().find(|lit| lit.0 != v && self.assigns[lit.0 as usize] == lit.1).map(|lit| lit.0)
+    }
+
+    // Flip v, updating the counts of only the clauses v occurs in
+    fn flip(&mut self, v: VarId) {
+        let val = !self.assigns[v as usize];
+        self.assigns[v as usize] = val;
+        for i in 0..self.occurs[v as usize].len() {
+            let ci = self.occurs[v as usize][i];
+            let now_true = self.f[ci].contains(&Literal(v, val));
+
+            if now_true {
+                self.num_true[ci] += 1;
+                match self.num_true[ci] {
+                    // v is now the only thing satisfying the clause
+                    1 => {
+                        self.mark_sat(ci);
+                        for lit in &self.f[ci] {
+                            self.make_count[lit.0 as usize] -= 1;
+                        }
+                        self.break_count[v as usize] += 1;
+                    }
+                    // The previously critical literal is no longer the only true one
+                    2 => {
+                        let w = self.other_true(ci, v).unwrap();
+                        self.break_count[w as usize] -= 1;
+                    }
+                    _ => (),
+                }
+            } else {
+                self.num_true[ci] -= 1;
+                match self.num_true[ci] {
+                    0 => {
+                        self.mark_unsat(ci);
+                        for lit in &self.f[ci] {
+                            self.make_count[lit.0 as usize] += 1;
+                        }
+                        self.break_count[v as usize] -= 1;
+                    }
+                    // One true literal is left and it is now critical
+                    1 => {
+                        let w = self.other_true(ci, v).unwrap();
+                        self.break_count[w as usize] += 1;
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+// How local search chooses which variable of an unsatisfied clause to flip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LsHeuristic {
+    // Greedily take the largest make - break score, with a random walk step of probability p
+    Gsat,
+    // Take a variable breaking nothing if there is one, otherwise with probability p
+    // a random one and otherwise the one breaking the fewest clauses
+    WalkSatSkc,
+    // Take the best make - break score unless it is the clause's most recently flipped
+    // variable, in which case take the second best with probability p
+    Novelty,
+}
+
+// Pick the variable of clause to flip according to heuristic
+fn pick_var(state: &State, ci: usize, heuristic: LsHeuristic, p: f64, rng: &mut Rng) -> VarId {
+    let clause = &state.f[ci];
+    let score = |lit: &Literal| state.make_count[lit.0 as usize] as isize - state.break_count[lit.0 as usize] as isize;
+    match heuristic {
+        LsHeuristic::Gsat => {
+            if rng.unit() < p {
+                return clause[rng.below(clause.len())].0;
+            }
+            clause.iter().max_by_key(|lit| score(lit)).unwrap().0
+        }
+        LsHeuristic::WalkSatSkc => {
+            let breaks: Vec<usize> = clause.iter().map(|lit| state.break_count[lit.0 as usize]).collect();
+            let (best, &min) = breaks.iter().enumerate().min_by_key(|(_, &b)| b).unwrap();
+            if min > 0 && rng.unit() < p {
+                return clause[rng.below(clause.len())].0;
+            }
+            clause[best].0
+        }
+        LsHeuristic::Novelty => {
+            let mut ranked: Vec<(isize, VarId)> = clause.iter().map(|lit| (score(lit), lit.0)).collect();
+            ranked.sort_by_key(|&(sc, _)| std::cmp::Reverse(sc));
+            let newest = clause.iter().map(|lit| lit.0).max_by_key(|&v| state.flipped_at[v as usize]).unwrap();
+            if ranked[0].1 == newest && ranked.len() > 1 && rng.unit() < p {
+                return ranked[1].1;
+            }
+            ranked[0].1
+        }
+    }
+}
+
+// Search for a model with WalkSAT, starting from a random assignment and flipping at most
+// max_flips times, see LsHeuristic::WalkSatSkc. Being incomplete it returns None both when
+// the formula is unsatisfiable and when the budget runs out.
+pub fn walksat(f: &[Vec<Literal>], num_vars: usize, max_flips: usize, p: f64, seed: u64) -> Option<Vec<bool>> {
+    local_search(f, num_vars, max_flips, p, seed, LsHeuristic::WalkSatSkc)
+}
+
+// Local search where each step repairs a random unsatisfied clause by flipping
+// the variable chosen by heuristic, with p as the heuristic's noise parameter
+pub fn local_search(
+    f: &[Vec<Literal>],
+    num_vars: usize,
+    max_flips: usize,
+    p: f64,
+    seed: u64,
+    heuristic: LsHeuristic,
+) -> Option<Vec<bool>> {
+    if f.iter().any(|c| c.is_empty()) {
+        return None;
+    }
+
+    let mut rng = Rng::new(seed);
+    let assigns = (0..num_vars).map(|_| rng.next_u64() & 1 == 1).collect();
+    let mut state = State::new(f, assigns);
+
+    for step in 1..=max_flips {
+        if state.unsat.is_empty() {
+            break;
+        }
+        let ci = state.unsat[rng.below(state.unsat.len())];
+        let v = pick_var(&state, ci, heuristic, p, &mut rng);
+        state.flip(v);
+        state.flipped_at[v as usize] = step;
+    }
+
+    state.unsat.is_empty().then_some(state.assigns)
+}
+
+// Try WalkSAT for ls_flips flips first and fall back to the complete solver
+// if it finds nothing, so satisfiable formulas can be answered quickly
+// without giving up on proving unsatisfiability
+pub fn solve_hybrid(f: &[Vec<Literal>], num_vars: usize, ls_flips: usize) -> Solution {
+    match walksat(f, num_vars, ls_flips, 0.5, 0) {
+        Some(model) => Solution::Sat(model),
+        None => solve(f, num_vars),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{certify::{certify_sat, CertResult}, cnf_parsing::parse_cnf_file_into};
+
+    #[test]
+    fn check_walksat() {
+        let mut c: Vec<Vec<Literal>> = Vec::new();
+        parse_cnf_file_into("cnfs/uf20-01.cnf", &mut c).unwrap();
+
+        let model = walksat(&c, 20, 100_000, 0.5, 42).expect("WalkSAT should find a model");
+        assert_eq!(certify_sat(&c, &model), CertResult::Valid);
+    }
+
+    #[test]
+    fn check_walksat_unsat() {
+        let c = vec![vec![Literal(0, true)], vec![Literal(0, false)]];
+        assert_eq!(walksat(&c, 1, 1000, 0.5, 1), None);
+        assert_eq!(walksat(&[vec![]], 1, 1000, 0.5, 1), None);
+    }
+
+    #[test]
+    fn check_solve_hybrid() {
+        let mut c: Vec<Vec<Literal>> = Vec::new();
+        parse_cnf_file_into("cnfs/uf20-01.cnf", &mut c).unwrap();
+        let Solution::Sat(model) = solve_hybrid(&c, 20, 10_000) else {
+            panic!("Expected a model.");
+        };
+        assert_eq!(certify_sat(&c, &model), CertResult::Valid);
+
+        // Local search can never succeed here, so the answer comes from the complete solver
+        let d = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, true), Literal(1, false)],
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(0, false), Literal(1, false)],
+        ];
+        assert_eq!(solve_hybrid(&d, 2, 1000), Solution::Unsat);
+    }
+
+    #[test]
+    fn check_ls_heuristics() {
+        let mut c: Vec<Vec<Literal>> = Vec::new();
+        parse_cnf_file_into("cnfs/uf20-01.cnf", &mut c).unwrap();
+
+        for h in [LsHeuristic::Gsat, LsHeuristic::WalkSatSkc, LsHeuristic::Novelty] {
+            let model = local_search(&c, 20, 100_000, 0.5, 7, h)
+                .unwrap_or_else(|| panic!("{h:?} should find a model"));
+            assert_eq!(certify_sat(&c, &model), CertResult::Valid);
+        }
+    }
+
+    #[test]
+    fn check_incremental_counts() {
+        let mut c: Vec<Vec<Literal>> = Vec::new();
+        parse_cnf_file_into("cnfs/uf20-01.cnf", &mut c).unwrap();
+        // A repeated literal and a tautology exercise the normalisation
+        c.push(vec![Literal(3, true), Literal(3, true), Literal(7, false)]);
+        c.push(vec![Literal(5, true), Literal(5, false)]);
+
+        let mut rng = Rng::new(3);
+        let assigns = (0..20).map(|_| rng.next_u64() & 1 == 1).collect();
+        let mut state = State::new(&c, assigns);
+
+        for _ in 0..500 {
+            state.flip(rng.below(20) as VarId);
+
+            let fresh = State::new(&c, state.assigns.clone());
+            assert_eq!(state.break_count, fresh.break_count);
+            assert_eq!(state.make_count, fresh.make_count);
+            assert_eq!(state.num_true, fresh.num_true);
+            let mut unsat = state.unsat.clone();
+            unsat.sort_unstable();
+            assert_eq!(unsat, fresh.unsat);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// A model packed 64 variables to a word
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitModel {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitModel {
+    fn pack(len: usize, value: impl Fn(usize) -> bool) -> Self {
+        let mut words = vec![0; len.div_ceil(64)];
+        for v in (0..len).filter(|&v| value(v)) {
+            words[v / 64] |= 1 << (v % 64);
+        }
+        BitModel { words, len }
+    }
+
+    // Pack a partial assignment, unconstrained variables are set to true
+    pub fn from_assigns(var_assigns: &[Option<bool>]) -> Self {
+        Self::pack(var_assigns.len(), |v| var_assigns[v].unwrap_or(true))
+    }
+
+    pub fn from_bools(model: &[bool]) -> Self {
+        Self::pack(model.len(), |v| model[v])
+    }
+
+    // The value of variable var, which must be below len()
+    pub fn get(&self, var: usize) -> bool {
+        assert!(var < self.len, "Variable {var} is out of range for a model of {} variables.", self.len);
+        self.words[var / 64] >> (var % 64) & 1 == 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // The packed words, variable v is bit v % 64 of word v / 64
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn to_bools(&self) -> Vec<bool> {
+        (0..self.len).map(|v| self.get(v)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_bit_model() {
+        let bools: Vec<bool> = (0..130).map(|v| v % 3 == 0).collect();
+        let m = BitModel::from_bools(&bools);
+        assert_eq!(m.len(), 130);
+        assert_eq!(m.words().len(), 3);
+        assert_eq!(m.to_bools(), bools);
+
+        let m = BitModel::from_assigns(&[Some(false), None, Some(true)]);
+        assert_eq!(m.to_bools(), vec![false, true, true]);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{dpll::{solve, Literal, Solution, VarId}, encodings::sequential_at_most_k};
+
+#[inline(always)]
+fn cost(model: &[bool], objective: &[Literal]) -> usize {
+    objective.iter().filter(|lit| model[lit.0 as usize] == lit.1).count()
+}
+
+// Find a model of f minimising how many literals of objective are true. Each time a model is
+// found the next solve requires strictly fewer true objective literals through a cardinality
+// constraint, until that becomes unsatisfiable. Unsat if f is, and Unknown if any solve along
+// the way could not decide its formula, as the last model found is then not known to be optimal.
+pub fn minimize(f: &[Vec<Literal>], num_vars: usize, objective: &[Literal]) -> Solution {
+    let mut best = match solve(f, num_vars) {
+        Solution::Sat(model) => model,
+        res => return res,
+    };
+
+    let mut bound = cost(&best, objective);
+    while bound > 0 {
+        let mut next_var = num_vars as VarId;
+        let mut g = f.to_vec();
+        g.extend(sequential_at_most_k(objective, bound - 1, &mut next_var));
+
+        match solve(&g, next_var as usize) {
+            Solution::Sat(mut model) => {
+                model.truncate(num_vars);
+                bound = cost(&model, objective);
+                best = model;
+            }
+            Solution::Unsat => break,
+            Solution::Unknown => return Solution::Unknown,
+        }
+    }
+    Solution::Sat(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certify::{certify_sat, CertResult};
+
+    #[test]
+    fn check_minimize() {
+        // Each clause needs one of its variables, 1 and 3 together cover them all
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(1, true), Literal(2, true)],
+            vec![Literal(2, true), Literal(3, true)],
+            vec![Literal(3, true), Literal(4, true), Literal(0, true)],
+            vec![Literal(1, true), Literal(4, true)],
+        ];
+        let objective: Vec<Literal> = (0..5).map(|v| Literal(v, true)).collect();
+
+        let Solution::Sat(model) = minimize(&c, 5, &objective) else {
+            panic!("Expected a model.");
+        };
+        assert_eq!(certify_sat(&c, &model), CertResult::Valid);
+
+        let optimum = (0..1u32 << 5)
+            .map(|bits| (0..5).map(|v| bits >> v & 1 == 1).collect::<Vec<bool>>())
+            .filter(|m| certify_sat(&c, m) == CertResult::Valid)
+            .map(|m| cost(&m, &objective))
+            .min()
+            .unwrap();
+        assert_eq!(optimum, 2);
+        assert_eq!(cost(&model, &objective), optimum);
+    }
+
+    #[test]
+    fn check_minimize_unsat() {
+        let c = vec![vec![Literal(0, true)], vec![Literal(0, false)]];
+        assert_eq!(minimize(&c, 1, &[Literal(0, true)]), Solution::Unsat);
+    }
+}
@@ -0,0 +1,456 @@
+use std::collections::HashSet;
+
+use crate::{dpll::{full_unit_prop, pure_lit_elim, Literal, VarId}, proof::resolve};
+
+// Upper bound on how many clauses implied_binaries returns, the closure can be quadratic in size
+pub const MAX_IMPLIED_BINARIES: usize = 10_000;
+
+// Variables occurring in more clauses than this are skipped by variable elimination by default
+pub const DEFAULT_MAX_OCCURRENCES: usize = 16;
+
+// Every binary clause (a ∨ b) gives the implications ¬a → b and ¬b → a
+fn implication_graph(f: &[Vec<Literal>], num_vars: usize) -> Vec<Vec<usize>> {
+    let mut adj = vec![Vec::new(); 2 * num_vars];
+    for clause in f {
+        if let [a, b] = clause[..] {
+            adj[Literal(a.0, !a.1).index()].push(b.index());
+            adj[Literal(b.0, !b.1).index()].push(a.index());
+        }
+    }
+    adj
+}
+
+// Find the strongly connected components of a graph (Kosaraju),
+// returns the component id of each node
+fn scc(adj: &[Vec<usize>]) -> Vec<usize> {
+    let n = adj.len();
+
+    // First pass: order nodes by finishing time
+    let mut visited = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    for start in 0..n {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut stack = vec![(start, 0)];
+        while let Some((u, i)) = stack.pop() {
+            if i < adj[u].len() {
+                stack.push((u, i + 1));
+                let v = adj[u][i];
+                if !visited[v] {
+                    visited[v] = true;
+                    stack.push((v, 0));
+                }
+            } else {
+                order.push(u);
+            }
+        }
+    }
+
+    // Second pass: flood the transposed graph in reverse finishing order
+    let mut radj = vec![Vec::new(); n];
+    for (u, vs) in adj.iter().enumerate() {
+        for &v in vs {
+            radj[v].push(u);
+        }
+    }
+    let mut comp = vec![usize::MAX; n];
+    let mut id = 0;
+    for &start in order.iter().rev() {
+        if comp[start] != usize::MAX {
+            continue;
+        }
+        comp[start] = id;
+        let mut stack = vec![start];
+        while let Some(u) = stack.pop() {
+            for &v in &radj[u] {
+                if comp[v] == usize::MAX {
+                    comp[v] = id;
+                    stack.push(v);
+                }
+            }
+        }
+        id += 1;
+    }
+    comp
+}
+
+// Replace every literal with its representative, dropping the clauses that
+// become tautologies and any repeated literals
+fn substitute(f: &mut Vec<Vec<Literal>>, mapping: &[Literal]) {
+    f.retain_mut(|clause| {
+        for lit in clause.iter_mut() {
+            let m = mapping[lit.0 as usize];
+            *lit = Literal(m.0, m.1 == lit.1);
+        }
+        clause.sort_unstable_by_key(|lit| lit.index());
+        clause.dedup();
+        !clause.windows(2).any(|w| w[0].0 == w[1].0)
+    });
+}
+
+// Detect equivalent literals through cycles of binary clauses and replace
+// each class by its lowest numbered variable. Returns the mapping from every
+// variable to the literal it is equal to, for use with reconstruct_model.
+// If a variable is found equivalent to its own negation nothing is substituted,
+// an empty clause is added and every variable is mapped to itself.
+pub fn equivalent_literal_substitution(f: &mut Vec<Vec<Literal>>, num_vars: usize) -> Vec<Literal> {
+    let comp = scc(&implication_graph(f, num_vars));
+    let lit = |v: usize, b: bool| Literal(v as VarId, b);
+
+    if (0..num_vars).any(|v| comp[lit(v, true).index()] == comp[lit(v, false).index()]) {
+        f.push(vec![]);
+        return (0..num_vars).map(|v| lit(v, true)).collect();
+    }
+
+    let mut reps: Vec<Option<Literal>> = vec![None; 2 * num_vars];
+    let mut mapping = Vec::with_capacity(num_vars);
+    for v in 0..num_vars {
+        let pos = comp[lit(v, true).index()];
+        let neg = comp[lit(v, false).index()];
+        if reps[pos].is_none() {
+            reps[pos] = Some(lit(v, true));
+            reps[neg] = Some(lit(v, false));
+        }
+        mapping.push(reps[pos].unwrap());
+    }
+
+    substitute(f, &mapping);
+    mapping
+}
+
+// Extend a model of the substituted formula to the variables that were substituted away
+pub fn reconstruct_model(model: &mut [bool], mapping: &[Literal]) {
+    for (v, m) in mapping.iter().enumerate() {
+        model[v] = model[m.0 as usize] == m.1;
+    }
+}
+
+// Eliminate each variable in turn by replacing the clauses it occurs in with all their
+// non-tautological resolvents on it, whenever there are no more of those than clauses removed.
+// Variables in more than max_occurrences clauses are never eliminated, the resolvents of a
+// dense variable are costly to compute and almost never few enough. Returns each eliminated
+// variable with the clauses it was removed from, in order, for use with reconstruct_eliminated.
+pub fn bounded_variable_elimination(
+    f: &mut Vec<Vec<Literal>>,
+    num_vars: usize,
+    max_occurrences: usize,
+) -> Vec<(VarId, Vec<Vec<Literal>>)> {
+    // A tautology would resolve with itself into a clause it does not imply
+    normalize(f);
+    let mut eliminated = Vec::new();
+    for v in 0..num_vars as VarId {
+        let occurs = |c: &Vec<Literal>| c.iter().any(|lit| lit.0 == v);
+        let count = f.iter().filter(|c| occurs(c)).count();
+        if count == 0 || count > max_occurrences {
+            continue;
+        }
+
+        let (pos, neg): (Vec<&Vec<Literal>>, Vec<&Vec<Literal>>) = f.iter()
+            .filter(|c| occurs(c))
+            .partition(|c| c.contains(&Literal(v, true)));
+        let resolvents: Vec<Vec<Literal>> = pos.iter()
+            .flat_map(|a| neg.iter().filter_map(|b| resolve(a, b, v)))
+            .collect();
+        if resolvents.len() > count {
+            continue;
+        }
+
+        let (removed, kept) = std::mem::take(f).into_iter().partition(|c| occurs(c));
+        *f = kept;
+        f.extend(resolvents);
+        eliminated.push((v, removed));
+    }
+    eliminated
+}
+
+// Extend a model of the formula left by bounded_variable_elimination to the variables it eliminated
+pub fn reconstruct_eliminated(model: &mut [bool], eliminated: &[(VarId, Vec<Vec<Literal>>)]) {
+    for (v, clauses) in eliminated.iter().rev() {
+        // True only if a clause with it positive is not satisfied otherwise. Then every clause
+        // with it negative must be, or their resolvent would be false.
+        model[*v as usize] = clauses.iter().any(|c| {
+            c.contains(&Literal(*v, true)) && !c.iter().any(|lit| lit.0 != *v && model[lit.0 as usize] == lit.1)
+        });
+    }
+}
+
+// Binary clauses implied by chains of binary implications that are not already in f,
+// each in sorted literal order. At most MAX_IMPLIED_BINARIES are returned.
+pub fn implied_binaries(f: &[Vec<Literal>], num_vars: usize) -> Vec<Vec<Literal>> {
+    let adj = implication_graph(f, num_vars);
+    let pair = |a: Literal, b: Literal| if a.index() <= b.index() { (a, b) } else { (b, a) };
+
+    let mut known: HashSet<(Literal, Literal)> = f.iter()
+        .filter_map(|clause| match clause[..] {
+            [a, b] => Some(pair(a, b)),
+            _ => None,
+        })
+        .collect();
+
+    let mut implied = Vec::new();
+    for start in 0..adj.len() {
+        // Everything reachable from a is implied by it, giving the clause (¬a ∨ b)
+        let a = Literal::from_index(start);
+        let mut seen = vec![false; adj.len()];
+        seen[start] = true;
+        let mut stack = vec![start];
+        while let Some(u) = stack.pop() {
+            for &v in &adj[u] {
+                if seen[v] {
+                    continue;
+                }
+                seen[v] = true;
+                stack.push(v);
+
+                let b = Literal::from_index(v);
+                if b.0 == a.0 {
+                    // a → ¬a only says a is false, which is not a binary clause
+                    continue;
+                }
+                let (x, y) = pair(Literal(a.0, !a.1), b);
+                if known.insert((x, y)) {
+                    implied.push(vec![x, y]);
+                    if implied.len() == MAX_IMPLIED_BINARIES {
+                        return implied;
+                    }
+                }
+            }
+        }
+    }
+    implied
+}
+
+// Remove every clause containing both polarities of a variable and collapse repeated
+// literals within the rest, keeping the order of what remains. Satisfiability is unchanged,
+// and an empty clause is left in place.
+pub fn normalize(f: &mut Vec<Vec<Literal>>) {
+    f.retain(|c| !c.iter().any(|lit| c.contains(&Literal(lit.0, !lit.1))));
+    for c in f.iter_mut() {
+        let mut seen = HashSet::new();
+        c.retain(|&lit| seen.insert(lit));
+    }
+}
+
+// Which clauses of f to keep when dropping every one containing all the literals of a kept
+// clause that may_subsume allows to stand in for others, keeping the first of any duplicates
+pub(crate) fn unsubsumed(f: &[Vec<Literal>], may_subsume: impl Fn(usize) -> bool) -> Vec<bool> {
+    let mut order: Vec<usize> = (0..f.len()).collect();
+    order.sort_by_key(|&i| f[i].len());
+
+    let mut keep = vec![false; f.len()];
+    let mut kept: Vec<usize> = Vec::new();
+    for i in order {
+        if !kept.iter().any(|&j| f[j].iter().all(|lit| f[i].contains(lit))) {
+            keep[i] = true;
+            if may_subsume(i) {
+                kept.push(i);
+            }
+        }
+    }
+    keep
+}
+
+// Drop every clause containing all the literals of another, keeping the first of any duplicates
+fn remove_subsumed(f: &mut Vec<Vec<Literal>>) {
+    let mut keep = unsubsumed(f, |_| true).into_iter();
+    f.retain(|_| keep.next().unwrap());
+}
+
+// A preprocessing pass recorded by simplify_with_snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepName {
+    UnitPropagation,
+    Subsumption,
+    PureLiteral,
+}
+
+// Simplify a formula with unit propagation, then subsumption, then pure literal
+// elimination, returning the formula as it stood after each of the passes
+pub fn simplify_with_snapshots(f: &[Vec<Literal>], num_vars: usize) -> Vec<(StepName, Vec<Vec<Literal>>)> {
+    let mut g = f.to_vec();
+    let mut var_assigns = vec![None; num_vars];
+    let mut snapshots = Vec::new();
+
+    full_unit_prop(&mut var_assigns, &mut g);
+    snapshots.push((StepName::UnitPropagation, g.clone()));
+    remove_subsumed(&mut g);
+    snapshots.push((StepName::Subsumption, g.clone()));
+    // Pure literal elimination leaves a unit clause behind for each variable it fixes
+    pure_lit_elim(&mut var_assigns, &mut g);
+    full_unit_prop(&mut var_assigns, &mut g);
+    snapshots.push((StepName::PureLiteral, g));
+    snapshots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{certify::{certify_sat, CertResult}, dpll::dpll};
+
+    #[test]
+    fn check_els() {
+        // 0 → 1 → ¬2 → 0 makes 0, 1 and ¬2 equivalent
+        let orig = vec![
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(1, false), Literal(2, false)],
+            vec![Literal(2, true), Literal(0, true)],
+            vec![Literal(1, true), Literal(3, true)],
+            vec![Literal(2, true), Literal(3, false), Literal(0, true)],
+        ];
+        let mut c = orig.clone();
+        let mapping = equivalent_literal_substitution(&mut c, 4);
+
+        assert_eq!(mapping, vec![
+            Literal(0, true),
+            Literal(0, true),
+            Literal(0, false),
+            Literal(3, true),
+        ]);
+        assert_eq!(c, vec![
+            vec![Literal(0, true), Literal(3, true)],
+        ]);
+
+        let mut vs = [None; 4];
+        assert!(dpll(&mut vs, &mut c));
+        let mut model: Vec<bool> = vs.iter().map(|v| v.unwrap_or(true)).collect();
+        reconstruct_model(&mut model, &mapping);
+        assert_eq!(model[0], model[1]);
+        assert_eq!(model[0], !model[2]);
+        assert_eq!(certify_sat(&orig, &model), CertResult::Valid);
+    }
+
+    #[test]
+    fn check_els_contradiction() {
+        let mut c = vec![
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(1, false), Literal(0, false)],
+            vec![Literal(0, true), Literal(1, false)],
+            vec![Literal(1, true), Literal(0, true)],
+        ];
+        let orig = c.clone();
+        let mapping = equivalent_literal_substitution(&mut c, 2);
+        // 0 and ¬0 are equivalent, which must not map 0 to ¬0
+        assert_eq!(mapping, [Literal(0, true), Literal(1, true)]);
+        assert_eq!(c[..orig.len()], orig);
+        assert_eq!(c[orig.len()..], [vec![]]);
+    }
+
+    #[test]
+    fn check_bounded_variable_elimination() {
+        // 0 and 4 occur twice each, 3 occurs in five clauses and 1 and 2 in three
+        let orig = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, false), Literal(2, true)],
+            vec![Literal(3, true), Literal(1, false)],
+            vec![Literal(3, true), Literal(2, false)],
+            vec![Literal(3, false), Literal(4, true)],
+            vec![Literal(3, false), Literal(1, true), Literal(2, true)],
+            vec![Literal(3, true), Literal(4, false)],
+        ];
+        let mut c = orig.clone();
+        let eliminated = bounded_variable_elimination(&mut c, 5, 2);
+        assert_eq!(eliminated, vec![
+            (0, orig[..2].to_vec()),
+            (4, vec![orig[4].clone(), orig[6].clone()]),
+        ]);
+        assert_eq!(c, vec![
+            orig[2].clone(),
+            orig[3].clone(),
+            orig[5].clone(),
+            vec![Literal(1, true), Literal(2, true)],
+        ]);
+
+        let mut vs = [None; 5];
+        assert!(dpll(&mut vs, &mut c));
+        let mut model: Vec<bool> = vs.iter().map(|v| v.unwrap_or(true)).collect();
+        reconstruct_eliminated(&mut model, &eliminated);
+        assert_eq!(certify_sat(&orig, &model), CertResult::Valid);
+
+        let mut c = vec![vec![Literal(0, true)], vec![Literal(0, false)]];
+        bounded_variable_elimination(&mut c, 1, DEFAULT_MAX_OCCURRENCES);
+        assert_eq!(c, vec![Vec::<Literal>::new()]);
+    }
+
+    #[test]
+    fn check_implied_binaries() {
+        // 0 → 1 → 2 and 3 → ¬0
+        let c = vec![
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(1, false), Literal(2, true)],
+            vec![Literal(0, true), Literal(3, true), Literal(2, false)],
+        ];
+        assert_eq!(implied_binaries(&c, 4), vec![vec![Literal(0, false), Literal(2, true)]]);
+
+        let d = vec![
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(1, false), Literal(2, true)],
+            vec![Literal(2, false), Literal(3, false)],
+        ];
+        let mut implied = implied_binaries(&d, 4);
+        implied.sort_unstable_by_key(|c| (c[0].index(), c[1].index()));
+        assert_eq!(implied, vec![
+            vec![Literal(0, false), Literal(2, true)],
+            vec![Literal(0, false), Literal(3, false)],
+            vec![Literal(1, false), Literal(3, false)],
+        ]);
+    }
+
+    #[test]
+    fn check_simplify_with_snapshots() {
+        let c = vec![
+            vec![Literal(0, true)],
+            vec![Literal(0, false), Literal(1, true), Literal(2, true)],
+            vec![Literal(1, true), Literal(2, true), Literal(3, true)],
+            vec![Literal(2, true), Literal(1, true)],
+            vec![Literal(3, true), Literal(4, true)],
+            vec![Literal(3, false), Literal(4, false)],
+        ];
+        let snapshots = simplify_with_snapshots(&c, 5);
+        let steps: Vec<(StepName, usize)> = snapshots.iter().map(|(s, g)| (*s, g.len())).collect();
+        assert_eq!(steps, [(StepName::UnitPropagation, 5), (StepName::Subsumption, 3), (StepName::PureLiteral, 2)]);
+
+        // 1 and 2 were pure, leaving only the clauses over 3 and 4
+        assert_eq!(snapshots[2].1, c[4..]);
+    }
+
+    #[test]
+    fn check_normalize() {
+        let mut c = vec![
+            vec![Literal(0, true), Literal(0, false)],
+            vec![Literal(0, false), Literal(1, true), Literal(0, false)],
+            vec![],
+            vec![Literal(2, true), Literal(1, false), Literal(3, true), Literal(2, false)],
+            vec![Literal(1, true)],
+        ];
+        normalize(&mut c);
+        assert_eq!(c, vec![
+            vec![Literal(0, false), Literal(1, true)],
+            vec![],
+            vec![Literal(1, true)],
+        ]);
+
+        let mut d = vec![vec![Literal(0, true), Literal(0, true)], vec![Literal(0, false), Literal(1, true)]];
+        normalize(&mut d);
+        assert_eq!(d, vec![vec![Literal(0, true)], vec![Literal(0, false), Literal(1, true)]]);
+        assert!(crate::dpll::dpll(&mut [None; 2], &mut d));
+    }
+
+    #[test]
+    fn check_remove_subsumed() {
+        let mut c = vec![
+            vec![Literal(0, true), Literal(1, false), Literal(2, true)],
+            vec![Literal(1, false), Literal(0, true)],
+            vec![Literal(2, true)],
+            vec![Literal(1, false), Literal(0, true)],
+            vec![Literal(0, false), Literal(1, false)],
+        ];
+        remove_subsumed(&mut c);
+        assert_eq!(c, vec![
+            vec![Literal(1, false), Literal(0, true)],
+            vec![Literal(2, true)],
+            vec![Literal(0, false), Literal(1, false)],
+        ]);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{cdcl::Cdcl, dpll::{Literal, Solution, VarId}};
+
+#[inline(always)]
+fn value(lit: Literal, var_assigns: &[Option<bool>]) -> Option<bool> {
+    var_assigns[lit.0 as usize].map(|b| b == lit.1)
+}
+
+// Resolve two clauses on the variable pivot, neither clause may
+// contain a complementary pair other than the pivot
+fn resolvent(a: &[Literal], b: &[Literal], pivot: VarId) -> Vec<Literal> {
+    let mut r: Vec<Literal> = a.iter().chain(b).filter(|lit| lit.0 != pivot).copied().collect();
+    r.sort_unstable_by_key(|lit| (lit.0, lit.1));
+    r.dedup();
+    r
+}
+
+// The resolvent of a and b on the variable pivot, sorted and without repeated literals.
+// None if pivot does not occur with opposite polarities in the two clauses or if the
+// resolvent would be a tautology.
+pub fn resolve(a: &[Literal], b: &[Literal], pivot: VarId) -> Option<Vec<Literal>> {
+    let clashes = [true, false].into_iter()
+        .any(|p| a.contains(&Literal(pivot, p)) && b.contains(&Literal(pivot, !p)));
+    if !clashes {
+        return None;
+    }
+    let r = resolvent(a, b, pivot);
+    // Sorted by variable, so a complementary pair is always adjacent
+    if r.windows(2).any(|w| w[0].0 == w[1].0) {
+        return None;
+    }
+    Some(r)
+}
+
+// Refute the formula below the partial assignment, returning a clause made false by it.
+// Returns None if a satisfying assignment was found instead.
+fn refute(
+    f: &[Vec<Literal>],
+    var_assigns: &mut [Option<bool>],
+    steps: &mut Vec<(Vec<Literal>, Vec<Literal>)>,
+) -> Option<Vec<Literal>> {
+    let mut branch = None;
+    for clause in f {
+        let mut unassigned = clause.iter().filter(|lit| value(**lit, var_assigns).is_none());
+        if clause.iter().any(|lit| value(*lit, var_assigns) == Some(true)) {
+            continue;
+        }
+        match (unassigned.next(), unassigned.next()) {
+            // Every literal is false
+            (None, _) => return Some(clause.clone()),
+            // Branching on the open literal of a unit clause refutes one side immediately
+            (Some(lit), None) => branch = Some(lit.0),
+            (Some(lit), Some(_)) => {
+                branch = branch.or(Some(lit.0));
+            }
+        }
+    }
+
+    // Every clause is satisfied
+    let x = branch?;
+
+    let mut parents = Vec::with_capacity(2);
+    for b in [true, false] {
+        var_assigns[x as usize] = Some(b);
+        let c = refute(f, var_assigns, steps);
+        var_assigns[x as usize] = None;
+        let c = c?;
+
+        // If the clause does not mention x it is already false without it
+        if !c.contains(&Literal(x, !b)) {
+            return Some(c);
+        }
+        parents.push(c);
+    }
+
+    let r = resolvent(&parents[0], &parents[1], x);
+    let b = parents.pop().unwrap();
+    let a = parents.pop().unwrap();
+    steps.push((a, b));
+    Some(r)
+}
+
+// Find a resolution refutation of an unsatisfiable formula, as the sequence of
+// parent clause pairs resolved on their single clashing variable. Every parent
+// is either an original clause or the resolvent of an earlier step, and the
+// last step resolves to the empty clause. Returns None if the formula is satisfiable.
+// The search is exhaustive so this is only meant for small formulas.
+pub fn solve_unsat_trace(f: &[Vec<Literal>], num_vars: usize) -> Option<Vec<(Vec<Literal>, Vec<Literal>)>> {
+    let mut var_assigns = vec![None; num_vars];
+    let mut steps = Vec::new();
+    let empty = refute(f, &mut var_assigns, &mut steps)?;
+    debug_assert!(empty.is_empty());
+    Some(steps)
+}
+
+// The indices into f, as given, of the original clauses a refutation of it uses, in increasing
+// order. Nothing is reordered or simplified on the way, so the indices are stable for callers
+// keeping their own bookkeeping per clause. Returns None if the formula is satisfiable.
+pub fn unsat_core(f: &[Vec<Literal>], num_vars: usize) -> Option<Vec<usize>> {
+    let steps = solve_unsat_trace(f, num_vars)?;
+    let mut core: Vec<usize> = if steps.is_empty() {
+        // Refuted without resolving anything, so f contains the empty clause
+        f.iter().position(|c| c.is_empty()).into_iter().collect()
+    } else {
+        steps.iter()
+            .flat_map(|(a, b)| [a, b])
+            .filter_map(|parent| f.iter().position(|c| c == parent))
+            .collect()
+    };
+    core.sort_unstable();
+    core.dedup();
+    Some(core)
+}
+
+// The indices of the clauses of f that a CDCL refutation of it uses, in increasing order: those
+// appearing as antecedents in its DRAT proof, directly or through the learned clauses resolved
+// from them. Every one belongs to the lean kernel, the clauses used by at least one refutation,
+// though another refutation could use clauses this one does not. A satisfiable formula has none.
+pub fn lean_kernel(f: &[Vec<Literal>], num_vars: usize) -> Vec<usize> {
+    let mut solver = Cdcl::new(f, num_vars).with_antecedents();
+    if solver.solve() != Solution::Unsat {
+        return Vec::new();
+    }
+    solver.used_clauses().expect("A refuted formula has a refutation to trace.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Replay the steps, checking each parent is known and returning the final resolvent
+    fn replay(f: &[Vec<Literal>], steps: &[(Vec<Literal>, Vec<Literal>)]) -> Vec<Literal> {
+        let mut known: Vec<Vec<Literal>> = f.iter().map(|c| resolvent(c, &[], VarId::MAX)).collect();
+        let mut last = None;
+        for (a, b) in steps {
+            assert!(known.contains(&resolvent(a, &[], VarId::MAX)), "{a:?} was never derived");
+            assert!(known.contains(&resolvent(b, &[], VarId::MAX)), "{b:?} was never derived");
+            let pivot = a.iter().find(|l| b.contains(&Literal(l.0, !l.1))).unwrap().0;
+            let r = resolvent(a, b, pivot);
+            known.push(r.clone());
+            last = Some(r);
+        }
+        last.unwrap()
+    }
+
+    #[test]
+    fn check_unsat_trace_small() {
+        let c = vec![
+            vec![Literal(0, true)],
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(1, false)],
+        ];
+        let steps = solve_unsat_trace(&c, 2).unwrap();
+        assert_eq!(steps, vec![
+            (vec![Literal(0, false), Literal(1, true)], vec![Literal(0, true)]),
+            (vec![Literal(1, false)], vec![Literal(1, true)]),
+        ]);
+        assert_eq!(replay(&c, &steps), vec![]);
+    }
+
+    #[test]
+    fn check_unsat_trace() {
+        let c = vec![
+            vec![Literal(0, true), Literal(1, true)],
+            vec![Literal(0, true), Literal(1, false)],
+            vec![Literal(0, false), Literal(2, true)],
+            vec![Literal(0, false), Literal(2, false)],
+        ];
+        let steps = solve_unsat_trace(&c, 3).unwrap();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(replay(&c, &steps), vec![]);
+
+        let d = vec![vec![Literal(0, true), Literal(1, true)]];
+        assert_eq!(solve_unsat_trace(&d, 2), None);
+    }
+
+    #[test]
+    fn check_resolve() {
+        let a = [Literal(2, true), Literal(0, true), Literal(1, false)];
+        let b = [Literal(0, false), Literal(3, true), Literal(2, true)];
+        assert_eq!(resolve(&a, &b, 0), Some(vec![Literal(1, false), Literal(2, true), Literal(3, true)]));
+        assert_eq!(resolve(&[Literal(0, true)], &[Literal(0, false)], 0), Some(vec![]));
+    }
+
+    #[test]
+    fn check_resolve_tautology() {
+        let a = [Literal(0, true), Literal(1, true)];
+        let b = [Literal(0, false), Literal(1, false)];
+        assert_eq!(resolve(&a, &b, 0), None);
+    }
+
+    #[test]
+    fn check_resolve_missing_pivot() {
+        let a = [Literal(0, true), Literal(1, true)];
+        let b = [Literal(0, true), Literal(2, false)];
+        assert_eq!(resolve(&a, &b, 0), None);
+        assert_eq!(resolve(&a, &b, 3), None);
+    }
+
+    #[test]
+    fn check_unsat_core() {
+        let c = vec![
+            vec![Literal(2, true), Literal(3, true)],
+            vec![Literal(0, true)],
+            vec![Literal(3, false), Literal(2, true)],
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(1, false)],
+        ];
+        assert_eq!(unsat_core(&c, 4), Some(vec![1, 3, 4]));
+
+        // The same clauses in another order report the indices of that order
+        let order = [4, 2, 3, 0, 1];
+        let d: Vec<Vec<Literal>> = order.iter().map(|&i| c[i].clone()).collect();
+        let core = unsat_core(&d, 4).unwrap();
+        assert_eq!(core, vec![0, 2, 4]);
+        let core_clauses: Vec<Vec<Literal>> = core.iter().map(|&i| d[i].clone()).collect();
+        assert_eq!(solve_unsat_trace(&core_clauses, 4).map(|steps| steps.len()), Some(2));
+
+        assert_eq!(unsat_core(&[vec![Literal(0, true)], vec![]], 1), Some(vec![1]));
+        assert_eq!(unsat_core(&c[..4], 4), None);
+    }
+
+    #[test]
+    fn check_lean_kernel() {
+        // Three pigeons in two holes, every clause of which any refutation needs, with
+        // satisfiable clauses over other variables mixed in
+        let var = |p: VarId, h: VarId| p * 2 + h;
+        let mut c: Vec<Vec<Literal>> = vec![vec![Literal(6, true), Literal(7, true)]];
+        c.extend((0..3).map(|p| vec![Literal(var(p, 0), true), Literal(var(p, 1), true)]));
+        c.push(vec![Literal(6, false), Literal(7, true)]);
+        for h in 0..2 {
+            for p in 0..3 {
+                for q in p + 1..3 {
+                    c.push(vec![Literal(var(p, h), false), Literal(var(q, h), false)]);
+                }
+            }
+        }
+        let kernel = lean_kernel(&c, 8);
+        assert_eq!(kernel, vec![1, 2, 3, 5, 6, 7, 8, 9, 10]);
+
+        // Literals fixed without decisions are traced back to the units fixing them
+        let d = vec![
+            vec![Literal(0, true)],
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(2, true), Literal(3, true)],
+            vec![Literal(1, false), Literal(3, true)],
+            vec![Literal(3, false)],
+        ];
+        assert_eq!(lean_kernel(&d, 4), vec![0, 1, 3, 4]);
+
+        // Two separate contradictions, on 0 and on 1, of which the refutation needs one
+        let e = vec![
+            vec![Literal(0, true)],
+            vec![Literal(2, true), Literal(3, true)],
+            vec![Literal(0, false)],
+            vec![Literal(1, true)],
+            vec![Literal(1, false)],
+        ];
+        let kernel = lean_kernel(&e, 4);
+        let used: Vec<Vec<Literal>> = kernel.iter().map(|&i| e[i].clone()).collect();
+        assert!(kernel == [0, 2] || kernel == [3, 4], "{kernel:?}");
+        assert_eq!(unsat_core(&used, 4).map(|core| core.len()), Some(2));
+
+        assert_eq!(lean_kernel(&d[..4], 4), Vec::<usize>::new());
+        assert_eq!(lean_kernel(&[vec![]], 1), vec![0]);
+    }
+}
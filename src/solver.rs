@@ -0,0 +1,523 @@
+use std::{fmt::{self, Debug}, sync::mpsc::Receiver};
+
+use crate::{cdcl::Cdcl, dpll::{check_model, Literal, Solution}, heuristics::Strategy};
+
+// A formula kept around for repeated solving, with a stack of
+// assumptions that every solve treats as extra unit clauses.
+// Decisions are made by VSIDS unless another strategy is chosen.
+pub struct Solver {
+    // The loaded formula followed by any clauses added since
+    clauses: Vec<Vec<Literal>>,
+    num_original: usize,
+    num_vars: usize,
+    assumptions: Vec<Literal>,
+    cache: Option<Cache>,
+    strategy: Strategy,
+    // Kept across solves with its learned clauses and activities, so later queries start from
+    // what earlier ones found out. Assumptions are its first decisions rather than clauses.
+    cdcl: Cdcl<'static>,
+    // The minimal assumptions the formula contradicts, if the last solve ended in Unsat
+    core: Option<Vec<Literal>>,
+}
+
+// Recently solved formulas in normalised form, least recently used first
+struct Cache {
+    capacity: usize,
+    entries: Vec<(Vec<Vec<Literal>>, Solution)>,
+    hits: usize,
+}
+
+// Sort and deduplicate the literals of each clause and then the clauses themselves,
+// so formulas differing only in ordering or repetition compare equal
+fn cache_key(f: &[Vec<Literal>]) -> Vec<Vec<Literal>> {
+    let key = |lit: &Literal| (lit.0, lit.1);
+    let mut g: Vec<Vec<Literal>> = f.iter()
+        .map(|c| {
+            let mut c = c.clone();
+            c.sort_by_key(key);
+            c.dedup();
+            c
+        })
+        .collect();
+    g.sort_by(|a, b| a.iter().map(key).cmp(b.iter().map(key)));
+    g.dedup();
+    g
+}
+
+// Supplies clauses on demand instead of up front, as in lazy encodings or an SMT theory.
+// Given a candidate model of the clauses so far, returns a clause it violates, or None to accept it.
+pub trait LazyClauseSource {
+    fn on_conflict(&mut self, model_so_far: &[bool]) -> Option<Vec<Literal>>;
+}
+
+// A search over f deciding as strategy says, VSIDS being the search's own activities
+fn engine(f: &[Vec<Literal>], num_vars: usize, strategy: Strategy) -> Cdcl<'static> {
+    let cdcl = Cdcl::new(f, num_vars);
+    match strategy {
+        Strategy::Vsids => cdcl,
+        _ => cdcl.with_heuristic(strategy.heuristic(f, num_vars)),
+    }
+}
+
+// Solve the formula cdcl holds, clauses, under assumptions
+fn search(cdcl: &mut Cdcl, clauses: &[Vec<Literal>], assumptions: &[Literal]) -> Solution {
+    let solution = cdcl.solve_under(assumptions);
+    if let Solution::Sat(model) = &solution {
+        check_model(clauses, model);
+    }
+    solution
+}
+
+impl Solver {
+    pub fn new(clauses: Vec<Vec<Literal>>, num_vars: usize) -> Self {
+        let strategy = Strategy::default();
+        let cdcl = engine(&clauses, num_vars, strategy);
+        Solver { num_original: clauses.len(), clauses, num_vars, assumptions: Vec::new(), cache: None, strategy, cdcl, core: None }
+    }
+
+    // Make decisions with the given strategy, a static one scoring the clauses as they are now.
+    // The search starts over, forgetting anything learned.
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self.cdcl = engine(&self.clauses, self.num_vars, strategy);
+        self
+    }
+
+    // Remember the results of the last capacity distinct formulas solved, so solving
+    // the same clauses again, in whatever order, returns the earlier solution
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(Cache { capacity, entries: Vec::new(), hits: 0 });
+        self
+    }
+
+    // How many solves were answered from the cache
+    pub fn cache_hits(&self) -> usize {
+        self.cache.as_ref().map_or(0, |c| c.hits)
+    }
+
+    // Conflicts analysed over every solve so far
+    pub fn conflicts(&self) -> usize {
+        self.cdcl.conflicts()
+    }
+
+    // Permanently add a clause to the formula
+    pub fn add_clause(&mut self, clause: Vec<Literal>) {
+        self.cdcl.add_clause(&clause);
+        self.clauses.push(clause);
+    }
+
+    // Go back to the formula as it was loaded, dropping added clauses and assumptions. What was
+    // learned may depend on the added clauses, so the search starts over.
+    pub fn reset(&mut self) {
+        self.clauses.truncate(self.num_original);
+        self.assumptions.clear();
+        self.cdcl = engine(&self.clauses, self.num_vars, self.strategy);
+    }
+
+    pub fn push_assumption(&mut self, lit: Literal) {
+        self.assumptions.push(lit);
+    }
+
+    // Remove the most recently pushed assumption
+    pub fn pop_assumption(&mut self) -> Option<Literal> {
+        self.assumptions.pop()
+    }
+
+    pub fn assumptions(&self) -> &[Literal] {
+        &self.assumptions
+    }
+
+    // Solve under the assumption stack extended by assumptions for this one call only,
+    // leaving the formula and the stack as they were. As with solve, core then gives the
+    // minimal set of them, from either, to blame if the result is Unsat.
+    pub fn solve_under(&mut self, assumptions: &[Literal]) -> Solution {
+        let depth = self.assumptions.len();
+        self.assumptions.extend_from_slice(assumptions);
+        let solution = self.solve();
+        self.assumptions.truncate(depth);
+        solution
+    }
+
+    // The assumptions the last solve found unsatisfiable together with the formula, such that
+    // dropping any one of them makes the rest satisfiable. None unless it ended in Unsat.
+    pub fn core(&self) -> Option<&[Literal]> {
+        self.core.as_deref()
+    }
+
+    // Cut down the failed assumptions of the last refutation by deleting one at a time, keeping each
+    // deletion that leaves them refuted. A refutation only blames assumptions that every earlier
+    // deletion found necessary, so the core can shrink to what it blames without undoing any.
+    // Each attempt reuses whatever the ones before it learned.
+    fn minimal_core(&mut self) -> Vec<Literal> {
+        let mut core = self.cdcl.failed_assumptions().to_vec();
+        let mut i = 0;
+        while i < core.len() {
+            let without = [&core[..i], &core[i + 1..]].concat();
+            if self.cdcl.solve_under(&without) == Solution::Unsat {
+                core = self.cdcl.failed_assumptions().to_vec();
+            } else {
+                i += 1;
+            }
+        }
+        core
+    }
+
+    // Solve the formula under the current assumption stack. If it is unsatisfiable under them, core
+    // then gives a minimal set of them to blame.
+    pub fn solve(&mut self) -> Solution {
+        let hits = self.cache_hits();
+        let solution = self.solve_cached();
+        self.core = None;
+        if solution == Solution::Unsat {
+            // An answer from the cache leaves the search without a refutation of its own to go on
+            if self.cache_hits() > hits {
+                self.cdcl.solve_under(&self.assumptions);
+            }
+            self.core = Some(self.minimal_core());
+        }
+        solution
+    }
+
+    fn solve_cached(&mut self) -> Solution {
+        let Some(cache) = &mut self.cache else {
+            return search(&mut self.cdcl, &self.clauses, &self.assumptions);
+        };
+
+        let mut f = self.clauses.clone();
+        f.extend(self.assumptions.iter().map(|&lit| vec![lit]));
+        let key = cache_key(&f);
+        if let Some(i) = cache.entries.iter().position(|e| e.0 == key) {
+            cache.hits += 1;
+            let entry = cache.entries.remove(i);
+            let solution = entry.1.clone();
+            cache.entries.push(entry);
+            return solution;
+        }
+
+        let solution = search(&mut self.cdcl, &self.clauses, &self.assumptions);
+        if cache.capacity > 0 {
+            if cache.entries.len() == cache.capacity {
+                cache.entries.remove(0);
+            }
+            cache.entries.push((key, solution.clone()));
+        }
+        solution
+    }
+
+    // Solve, then keep adding the clauses source finds violated and solving again until
+    // it accepts a model or the clauses become unsatisfiable. Added clauses are kept.
+    pub fn solve_lazy(&mut self, source: &mut dyn LazyClauseSource) -> Solution {
+        loop {
+            let model = match self.solve() {
+                Solution::Sat(model) => model,
+                res => return res,
+            };
+            match source.on_conflict(&model) {
+                Some(clause) => self.add_clause(clause),
+                None => return Solution::Sat(model),
+            }
+        }
+    }
+}
+
+// Collect clauses from rx until every sender has hung up, then solve the assembled formula
+pub fn solve_from_channel(rx: Receiver<Vec<Literal>>, num_vars: usize) -> Solution {
+    let clauses = rx.into_iter().collect();
+    Solver::new(clauses, num_vars).solve()
+}
+
+// A compact summary rather than every clause, so dbg! stays readable on real formulas. The trail,
+// level and restarts are where the search stands, after a solve the trail is the model it found.
+impl Debug for Solver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Solver")
+            .field("vars", &self.num_vars)
+            .field("clauses", &self.num_original)
+            .field("added", &(self.clauses.len() - self.num_original))
+            .field("learned", &self.cdcl.learned().len())
+            .field("assumptions", &self.assumptions)
+            .field("level", &self.cdcl.decision_level())
+            .field("trail", &self.cdcl.trail())
+            .field("restarts", &self.cdcl.restarts())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_assumption_stack() {
+        let c = vec![
+            vec![Literal(0, false), Literal(1, false)],
+            vec![Literal(1, true), Literal(2, true)],
+        ];
+        let mut s = Solver::new(c, 3);
+
+        s.push_assumption(Literal(0, true));
+        s.push_assumption(Literal(2, false));
+        assert_eq!(s.solve(), Solution::Unsat);
+
+        assert_eq!(s.pop_assumption(), Some(Literal(2, false)));
+        assert_eq!(s.assumptions(), &[Literal(0, true)]);
+        assert_eq!(s.solve(), Solution::Sat(vec![true, false, true]));
+
+        s.pop_assumption();
+        assert!(matches!(s.solve(), Solution::Sat(_)));
+        assert_eq!(s.pop_assumption(), None);
+    }
+
+    #[test]
+    fn check_solve_under() {
+        // 0 → 1 → 2
+        let c = vec![
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(1, false), Literal(2, true)],
+        ];
+        let mut s = Solver::new(c, 3);
+        let contradictory = [Literal(0, true), Literal(2, false)];
+        let consistent = [Literal(0, true)];
+
+        for _ in 0..3 {
+            assert_eq!(s.solve_under(&contradictory), Solution::Unsat);
+            assert_eq!(s.solve_under(&consistent), Solution::Sat(vec![true; 3]));
+        }
+        assert_eq!(s.solve_under(&[Literal(1, true), Literal(1, false)]), Solution::Unsat);
+        assert!(s.assumptions().is_empty());
+        assert!(matches!(s.solve(), Solution::Sat(_)));
+
+        // They are added to whatever is already on the stack
+        s.push_assumption(Literal(2, false));
+        assert_eq!(s.solve_under(&consistent), Solution::Unsat);
+        assert_eq!(s.solve_under(&[]), Solution::Sat(vec![false, false, false]));
+        assert_eq!(s.assumptions(), &[Literal(2, false)]);
+    }
+
+    #[test]
+    fn check_learned_clauses_kept() {
+        // Five pigeons in four holes, but only once 20 is assumed to make every pigeon need a hole
+        let var = |p: u32, h: u32| p * 4 + h;
+        let mut c: Vec<Vec<Literal>> = (0..5)
+            .map(|p| (0..4).map(|h| Literal(var(p, h), true)).chain([Literal(20, false)]).collect())
+            .collect();
+        for h in 0..4 {
+            for p in 0..5 {
+                for q in p + 1..5 {
+                    c.push(vec![Literal(var(p, h), false), Literal(var(q, h), false)]);
+                }
+            }
+        }
+        let mut s = Solver::new(c, 21);
+
+        assert_eq!(s.solve_under(&[Literal(20, true)]), Solution::Unsat);
+        let first = s.conflicts();
+        assert!(first > 0);
+        // The second refutation starts from the clauses the first one learned
+        assert_eq!(s.solve_under(&[Literal(20, true)]), Solution::Unsat);
+        assert!(s.conflicts() - first < first, "{} then {}", first, s.conflicts() - first);
+        assert!(matches!(s.solve(), Solution::Sat(_)));
+        assert_eq!(s.solve_under(&[Literal(20, true)]), Solution::Unsat);
+    }
+
+    // Knows that 0 and 1 may not both be true, but only says so when asked
+    struct NotBoth {
+        calls: usize,
+    }
+
+    impl LazyClauseSource for NotBoth {
+        fn on_conflict(&mut self, model_so_far: &[bool]) -> Option<Vec<Literal>> {
+            self.calls += 1;
+            (model_so_far[0] && model_so_far[1]).then(|| vec![Literal(0, false), Literal(1, false)])
+        }
+    }
+
+    #[test]
+    fn check_solve_lazy() {
+        // The first candidate sets everything true
+        let c = vec![vec![Literal(0, true), Literal(2, true)], vec![Literal(1, true), Literal(2, false)]];
+        assert_eq!(Solver::new(c.clone(), 3).solve(), Solution::Sat(vec![true; 3]));
+
+        let mut s = Solver::new(c, 3);
+        let mut source = NotBoth { calls: 0 };
+        let Solution::Sat(model) = s.solve_lazy(&mut source) else {
+            panic!("Expected a model.");
+        };
+        assert!(!(model[0] && model[1]));
+        assert_eq!(source.calls, 2);
+        assert_eq!(format!("{s:?}"), "Solver { vars: 3, clauses: 2, added: 1, learned: 0, assumptions: [], level: 1, trail: [0, ¬1, ¬2], restarts: 0 }");
+
+        s.add_clause(vec![Literal(0, true)]);
+        s.add_clause(vec![Literal(1, true)]);
+        assert_eq!(s.solve_lazy(&mut source), Solution::Unsat);
+    }
+
+    #[test]
+    fn check_debug() {
+        let c = vec![
+            vec![Literal(0, false), Literal(1, false)],
+            vec![Literal(1, true), Literal(2, true)],
+        ];
+        let mut s = Solver::new(c, 3);
+        s.push_assumption(Literal(2, false));
+        assert_eq!(format!("{s:?}"), "Solver { vars: 3, clauses: 2, added: 0, learned: 0, assumptions: [¬2], level: 0, trail: [], restarts: 0 }");
+
+        // The assumption is decided at level 1 and forces the rest
+        assert_eq!(s.solve(), Solution::Sat(vec![false, true, false]));
+        assert_eq!(format!("{s:?}"), "Solver { vars: 3, clauses: 2, added: 0, learned: 0, assumptions: [¬2], level: 1, trail: [¬2, 1, ¬0], restarts: 0 }");
+        s.add_clause(vec![Literal(0, true), Literal(2, true)]);
+        assert_eq!(format!("{s:?}"), "Solver { vars: 3, clauses: 2, added: 1, learned: 0, assumptions: [¬2], level: 0, trail: [], restarts: 0 }");
+    }
+
+    #[test]
+    fn check_solve_from_channel() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let producer = std::thread::spawn(move || {
+            for v in 0..10u32 {
+                tx.send(vec![Literal(v, false), Literal(v + 1, true)]).unwrap();
+            }
+            tx.send(vec![Literal(0, true)]).unwrap();
+        });
+
+        // The chain forces every variable true once 0 is
+        assert_eq!(solve_from_channel(rx, 11), Solution::Sat(vec![true; 11]));
+        producer.join().unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            tx.send(vec![Literal(0, true)]).unwrap();
+            tx.send(vec![Literal(0, false)]).unwrap();
+        });
+        assert_eq!(solve_from_channel(rx, 1), Solution::Unsat);
+    }
+
+    #[test]
+    fn check_strategies() {
+        let c = vec![
+            vec![Literal(0, false), Literal(1, false)],
+            vec![Literal(1, true), Literal(2, true)],
+            vec![Literal(2, false), Literal(3, true), Literal(0, true)],
+        ];
+        for strategy in [Strategy::FirstUnassigned, Strategy::JeroslowWang, Strategy::Vsids] {
+            let mut s = Solver::new(c.clone(), 4).with_strategy(strategy);
+            let Solution::Sat(model) = s.solve() else {
+                panic!("Expected a model with {strategy:?}.");
+            };
+            assert_eq!(crate::certify::certify_sat(&c, &model), crate::certify::CertResult::Valid);
+            s.push_assumption(Literal(1, true));
+            s.push_assumption(Literal(0, true));
+            assert_eq!(s.solve(), Solution::Unsat);
+        }
+
+        // First unassigned tries 0 true first, Jeroslow-Wang prefers ¬0 from the shorter clause
+        assert_eq!(Solver::new(c.clone(), 4).with_strategy(Strategy::FirstUnassigned).solve(), Solution::Sat(vec![true, false, true, true]));
+        assert_eq!(Solver::new(c, 4).with_strategy(Strategy::JeroslowWang).solve(), Solution::Sat(vec![false, true, true, true]));
+    }
+
+    #[test]
+    fn check_reset() {
+        let c = vec![
+            vec![Literal(0, false), Literal(1, false)],
+            vec![Literal(1, true), Literal(2, true)],
+        ];
+        let mut s = Solver::new(c.clone(), 3);
+        s.add_clause(vec![Literal(1, true)]);
+        s.add_clause(vec![Literal(2, false)]);
+        s.push_assumption(Literal(0, true));
+        assert_eq!(s.solve(), Solution::Unsat);
+
+        s.reset();
+        let mut fresh = Solver::new(c, 3);
+        assert_eq!(format!("{s:?}"), format!("{fresh:?}"));
+        assert_eq!(s.solve(), fresh.solve());
+        assert!(matches!(s.solve(), Solution::Sat(_)));
+    }
+
+    #[test]
+    fn check_cache() {
+        let c = vec![
+            vec![Literal(0, false), Literal(1, false)],
+            vec![Literal(1, true), Literal(2, true)],
+        ];
+        let mut s = Solver::new(c.clone(), 3).with_cache(2);
+        let first = s.solve();
+        assert_eq!(s.cache_hits(), 0);
+        assert_eq!(s.solve(), first);
+        assert_eq!(s.cache_hits(), 1);
+
+        // The same clauses in another order, with a repeated literal, are the same formula
+        let mut t = Solver::new(vec![], 3).with_cache(2);
+        for clause in &c {
+            t.add_clause(clause.clone());
+        }
+        let solution = t.solve();
+        t.reset();
+        t.add_clause(vec![Literal(2, true), Literal(1, true), Literal(2, true)]);
+        t.add_clause(vec![Literal(1, false), Literal(0, false)]);
+        assert_eq!(t.cache_hits(), 0);
+        assert_eq!(t.solve(), solution);
+        assert_eq!(t.cache_hits(), 1);
+
+        // Assumptions are part of the formula, and the least recently used entry is evicted
+        s.push_assumption(Literal(0, true));
+        assert_eq!(s.solve(), Solution::Sat(vec![true, false, true]));
+        s.push_assumption(Literal(2, false));
+        assert_eq!(s.solve(), Solution::Unsat);
+        assert_eq!(s.cache_hits(), 1);
+        s.pop_assumption();
+        s.pop_assumption();
+        assert_eq!(s.solve(), first);
+        assert_eq!(s.cache_hits(), 1);
+    }
+
+    #[test]
+    fn check_core() {
+        // 0 → 1 → 2, so assuming 0 and ¬2 fails whatever else is assumed
+        let c = vec![
+            vec![Literal(0, false), Literal(1, true)],
+            vec![Literal(1, false), Literal(2, true)],
+        ];
+        let mut s = Solver::new(c, 5);
+        for lit in [Literal(3, true), Literal(0, true), Literal(4, false), Literal(2, false)] {
+            s.push_assumption(lit);
+        }
+        assert_eq!(s.solve(), Solution::Unsat);
+        assert_eq!(s.core(), Some(&[Literal(0, true), Literal(2, false)][..]));
+
+        s.pop_assumption();
+        assert!(matches!(s.solve(), Solution::Sat(_)));
+        assert_eq!(s.core(), None);
+
+        s.add_clause(vec![Literal(4, true)]);
+        assert_eq!(s.solve(), Solution::Unsat);
+        assert_eq!(s.core(), Some(&[Literal(4, false)][..]));
+        s.add_clause(vec![Literal(4, false)]);
+        assert_eq!(s.solve(), Solution::Unsat);
+        assert_eq!(s.core(), Some(&[][..]));
+    }
+
+    #[test]
+    fn check_minimal_core() {
+        // As in Cdcl's check_failed_assumptions, refuting 2 blames 0 and 1 though 1 alone would do
+        let c = vec![
+            vec![Literal(0, false), Literal(3, true)],
+            vec![Literal(1, false), Literal(3, false), Literal(4, true)],
+            vec![Literal(4, false), Literal(2, false)],
+            vec![Literal(1, false), Literal(5, true)],
+            vec![Literal(5, false), Literal(2, false)],
+        ];
+        let assumptions = [Literal(0, true), Literal(1, true), Literal(2, true)];
+        let mut raw = Cdcl::new(&c, 6);
+        assert_eq!(raw.solve_under(&assumptions), Solution::Unsat);
+        assert_eq!(raw.failed_assumptions(), assumptions);
+
+        let mut s = Solver::new(c, 6).with_cache(1);
+        assert_eq!(s.solve_under(&assumptions), Solution::Unsat);
+        assert_eq!(s.core(), Some(&assumptions[1..]));
+        assert!(s.assumptions().is_empty());
+        // The same from the cache
+        assert_eq!(s.solve_under(&assumptions), Solution::Unsat);
+        assert_eq!(s.cache_hits(), 1);
+        assert_eq!(s.core(), Some(&assumptions[1..]));
+    }
+}